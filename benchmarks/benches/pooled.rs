@@ -0,0 +1,133 @@
+#![feature(test)]
+
+use maud::{html, render_pooled, Markup, PooledHtml};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+extern crate test;
+
+/// Counts allocations, to show how many the pool saves.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const TEAMS: &[(&str, u16)] = &[("Jiangsu", 43), ("Beijing", 27), ("Guangzhou", 22), ("Shandong", 12)];
+
+fn page(year: &str) -> Markup {
+    html! {
+        html {
+            head { title { (year) } }
+            body {
+                h1 { "CSL " (year) }
+                ul {
+                    @for (i, &(name, score)) in TEAMS.iter().enumerate() {
+                        li.champion[i == 0] { b { (name) ": " (score) } }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn render_unpooled(year: &str) -> String {
+    page(year).into_string()
+}
+
+/// Copies a finished `Markup` into a pooled buffer, which saves nothing.
+fn render_copied(year: &str) -> PooledHtml {
+    render_pooled(&page(year), 1024)
+}
+
+fn render_option(year: &str) -> PooledHtml {
+    html!(pooled = true, {
+        html {
+            head { title { (year) } }
+            body {
+                h1 { "CSL " (year) }
+                ul {
+                    @for (i, &(name, score)) in TEAMS.iter().enumerate() {
+                        li.champion[i == 0] { b { (name) ": " (score) } }
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn render_writer(year: &str) -> PooledHtml {
+    let mut page = PooledHtml::with_capacity(1024);
+    html!(&mut page, {
+        html {
+            head { title { (year) } }
+            body {
+                h1 { "CSL " (year) }
+                ul {
+                    @for (i, &(name, score)) in TEAMS.iter().enumerate() {
+                        li.champion[i == 0] { b { (name) ": " (score) } }
+                    }
+                }
+            }
+        }
+    }).unwrap();
+    page
+}
+
+/// Renders the page 10,000 times and reports the allocations per render.
+fn count_allocations<T, F: FnMut() -> T>(label: &str, mut render: F) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..10_000 {
+        test::black_box(render());
+    }
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+    eprintln!("{}: {:.2} allocations per render", label, (after - before) as f64 / 10_000.0);
+}
+
+#[bench]
+fn unpooled(b: &mut test::Bencher) {
+    let year = test::black_box("2015");
+    count_allocations("unpooled", || render_unpooled(year));
+    b.iter(|| render_unpooled(year));
+}
+
+#[bench]
+fn pooled_copy(b: &mut test::Bencher) {
+    let year = test::black_box("2015");
+    assert_eq!(&*render_copied(year), render_unpooled(year));
+    count_allocations("render_pooled(&html!{..})", || render_copied(year));
+    b.iter(|| render_copied(year));
+}
+
+#[bench]
+fn pooled_option(b: &mut test::Bencher) {
+    let year = test::black_box("2015");
+    assert_eq!(&*render_option(year), render_unpooled(year));
+    count_allocations("html!(pooled = true, ..)", || render_option(year));
+    b.iter(|| render_option(year));
+}
+
+#[bench]
+fn pooled_writer(b: &mut test::Bencher) {
+    let year = test::black_box("2015");
+    assert_eq!(&*render_writer(year), render_unpooled(year));
+    count_allocations("html!(&mut pooled, ..)", || render_writer(year));
+    b.iter(|| render_writer(year));
+}
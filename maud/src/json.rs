@@ -0,0 +1,38 @@
+use serde::Serialize;
+
+use crate::Render;
+
+/// Renders a value as JSON that's safe to put in a `script` element.
+///
+/// The characters `<`, `>`, and `&` are written as `\u003c`, `\u003e`,
+/// and `\u0026`, so a string holding `</script>` can't end the element
+/// early. JSON parsers read them back as the original characters.
+///
+/// This is what [`@json_script`](https://maud.lambda.xyz/dynamic-content.html)
+/// uses. If the value can't be serialized, as with a map whose keys
+/// aren't strings, it's written as `null`.
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// use maud::{html, Json};
+///
+/// let markup = html! { script type="application/json" { (Json(&["</script>"])) } };
+/// assert_eq!(markup.into_string(), r#"<script type="application/json">["\u003c/script\u003e"]</script>"#);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> Render for Json<T> {
+    fn render_to(&self, w: &mut String) {
+        let json = serde_json::to_string(&self.0).unwrap_or_else(|_| "null".to_string());
+        w.reserve(json.len());
+        for c in json.chars() {
+            match c {
+                '<' => w.push_str("\\u003c"),
+                '>' => w.push_str("\\u003e"),
+                '&' => w.push_str("\\u0026"),
+                c => w.push(c),
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 #![feature(specialization)]
+#![allow(incomplete_features)]
 
 //! A macro for writing HTML templates.
 //!
@@ -14,8 +15,24 @@
 #[cfg(feature = "rocket")] extern crate rocket;
 
 use std::fmt::{self, Write};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::iter::FromIterator;
+use std::ops::{Add, AddAssign};
+use std::path::Path;
 
-pub use maud_macros::{html, html_debug};
+pub use maud_macros::{html, html_chunks, html_debug, html_include, html_string, template, Render};
+
+#[cfg(feature = "json")]
+mod json;
+mod node;
+mod pool;
+mod pretty;
+
+#[cfg(feature = "json")]
+pub use crate::json::Json;
+pub use crate::node::{Element, Node};
+pub use crate::pool::{PooledHtml, render_pooled};
 
 /// Represents a type that can be rendered as HTML.
 ///
@@ -30,6 +47,12 @@ pub use maud_macros::{html, html_debug};
 /// defined in terms of the other, you only need to implement one of
 /// them. See the example below.
 ///
+/// A type that implements `Display` can still have a custom `Render`
+/// impl, as long as it overrides `.render_to()` and the crate enables
+/// `#![feature(specialization)]`. Splices will then prefer `Render`;
+/// wrap the value in [`display`](fn.display.html) to use `Display`
+/// instead.
+///
 /// # Minimal implementation
 ///
 /// An implementation of this trait must override at least one of
@@ -74,34 +97,201 @@ pub trait Render {
     fn render_to(&self, buffer: &mut String) {
         buffer.push_str(&self.render().into_string());
     }
+
+    /// Appends a representation of `self` to the given buffer, using
+    /// `escape` in place of the usual HTML escaping.
+    ///
+    /// Splices call this in a template with the `escaper` option, which is
+    /// how to render with a custom escaper. Strings and other `Display`
+    /// types pass their text through `escape`, while markup, which is
+    /// already escaped, is written as is. The default just calls
+    /// `.render_to()`, so if you implement `Render` for a type that holds
+    /// text, then you should override this method as well.
+    fn render_escaped_to(&self, buffer: &mut String, _escape: &dyn Escape) {
+        self.render_to(buffer);
+    }
+}
+
+/// Escapes text for the `escaper` option of `html!`.
+///
+/// This is implemented for closures, so a custom escaper can be written
+/// as `|text: &str, buffer: &mut String| ...`.
+pub trait Escape {
+    /// Appends an escaped copy of `text` to the given buffer.
+    fn escape_to(&self, text: &str, buffer: &mut String);
+}
+
+impl<F: Fn(&str, &mut String)> Escape for F {
+    fn escape_to(&self, text: &str, buffer: &mut String) {
+        self(text, buffer)
+    }
+}
+
+/// Escapes HTML, and writes every non-ASCII character as a numeric
+/// entity, for transports that only allow ASCII.
+///
+/// Use it with the `escaper` option:
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// use maud::html;
+///
+/// let markup = html!(escaper = maud::escape_ascii, {
+///     p { "café" }
+/// });
+/// assert_eq!(markup.into_string(), "<p>caf&#233;</p>");
+/// ```
+///
+/// Markup that's already escaped, like `PreEscaped` text, is left alone.
+pub fn escape_ascii(text: &str, buffer: &mut String) {
+    for chunk in escape_chunks(text) {
+        if chunk.is_ascii() {
+            buffer.push_str(chunk);
+            continue;
+        }
+        for c in chunk.chars() {
+            if c.is_ascii() {
+                buffer.push(c);
+            } else {
+                let _ = write!(buffer, "&#{};", c as u32);
+            }
+        }
+    }
 }
 
 impl<T: fmt::Display + ?Sized> Render for T {
     default fn render_to(&self, w: &mut String) {
         let _ = write!(Escaper::new(w), "{}", self);
     }
+
+    default fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+        escape.escape_to(&self.to_string(), w);
+    }
 }
 
 impl Render for String {
     fn render_to(&self, w: &mut String) {
         let _ = Escaper::new(w).write_str(self);
     }
+
+    fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+        escape.escape_to(self, w);
+    }
 }
 
 impl Render for str {
     fn render_to(&self, w: &mut String) {
         let _ = Escaper::new(w).write_str(self);
     }
+
+    fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+        escape.escape_to(self, w);
+    }
 }
 
 /// A wrapper that renders the inner value without escaping.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct PreEscaped<T: AsRef<str>>(pub T);
 
 impl<T: AsRef<str>> Render for PreEscaped<T> {
     fn render_to(&self, w: &mut String) {
         w.push_str(self.0.as_ref());
     }
+
+    fn render_escaped_to(&self, w: &mut String, _: &dyn Escape) {
+        w.push_str(self.0.as_ref());
+    }
+}
+
+impl<T: AsRef<str>> PreEscaped<T> {
+    /// Copies the markup into a byte buffer, and returns the number of
+    /// bytes written, or `Err(Overflow)` if it doesn't fit.
+    ///
+    /// To render a template into the buffer without building a `Markup`
+    /// first, use [`render_into_buf`](fn.render_into_buf.html).
+    pub fn render_into_buf(&self, buf: &mut [u8]) -> Result<usize, Overflow> {
+        render_into_buf(|w| w.write_str(self.0.as_ref()), buf)
+    }
+
+    /// Wraps the markup in an element with the given name and attributes.
+    ///
+    /// The attribute values are escaped, but the element and attribute
+    /// names are written as is.
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// use maud::html;
+    ///
+    /// let field = html! { input name="email"; };
+    /// let field = field.wrap("div", &[("class", "error")]);
+    /// assert_eq!(field.into_string(), r#"<div class="error"><input name="email"></div>"#);
+    /// ```
+    pub fn wrap(&self, name: &str, attrs: &[(&str, &str)]) -> Markup {
+        let inner = self.0.as_ref();
+        let mut buffer = String::with_capacity(inner.len() + 2 * name.len() + 5);
+        buffer.push('<');
+        buffer.push_str(name);
+        for &(attr_name, value) in attrs {
+            rt::write_attr(&mut buffer, attr_name, value);
+        }
+        buffer.push('>');
+        buffer.push_str(inner);
+        buffer.push_str("</");
+        buffer.push_str(name);
+        buffer.push('>');
+        PreEscaped(buffer)
+    }
+}
+
+/// A wrapper that renders the inner value using its `Display` impl.
+///
+/// Use the [`display`](fn.display.html) function to construct this type.
+#[derive(Debug, Clone, Copy)]
+pub struct AsDisplay<T>(pub T);
+
+impl<T: fmt::Display> Render for AsDisplay<T> {
+    fn render_to(&self, w: &mut String) {
+        let _ = write!(Escaper::new(w), "{}", self.0);
+    }
+
+    fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+        escape.escape_to(&self.0.to_string(), w);
+    }
+}
+
+/// Renders a value as escaped text, even if it has a custom `Render` impl.
+///
+/// When a type implements both `Display` and `Render`, a splice will
+/// call its `Render` impl. Wrapping the value in `display()` forces the
+/// `Display` impl to be used instead.
+///
+/// # Example
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene, specialization)]
+/// # #![allow(incomplete_features)]
+/// use maud::{display, html, Render};
+/// use std::fmt;
+///
+/// struct Pony;
+///
+/// impl fmt::Display for Pony {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         f.write_str("<pony>")
+///     }
+/// }
+///
+/// impl Render for Pony {
+///     fn render_to(&self, buffer: &mut String) {
+///         buffer.push_str("<b>pony</b>");
+///     }
+/// }
+///
+/// let markup = html! { (Pony) " " (display(Pony)) };
+/// assert_eq!(markup.into_string(), "<b>pony</b> &lt;pony&gt;");
+/// ```
+pub fn display<T: fmt::Display>(value: T) -> AsDisplay<T> {
+    AsDisplay(value)
 }
 
 /// A block of markup is a string that does not need to be escaped.
@@ -114,15 +304,210 @@ impl<T: AsRef<str> + Into<String>> PreEscaped<T> {
     pub fn into_string(self) -> String {
         self.0.into()
     }
+
+    /// Like `into_string`, but in debug builds, checks that the tags in
+    /// the output are balanced and that quoted values are closed.
+    ///
+    /// Templates written with `html!` always balance, so this is for
+    /// catching broken `PreEscaped` text. In release builds, this is the
+    /// same as `into_string`.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if the output is malformed.
+    pub fn into_string_checked(self) -> String {
+        let string = self.into_string();
+        #[cfg(debug_assertions)]
+        {
+            if let Err(message) = crate::pretty::check_well_formed(&string) {
+                panic!("malformed markup: {}\n{}", message, string);
+            }
+        }
+        string
+    }
+
+    /// Escapes the whole of the markup, tags and all, so it can be put
+    /// inside a quoted attribute value as text.
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// use maud::html;
+    ///
+    /// let tooltip = html! { b title="hi" { "x" } };
+    /// assert_eq!(tooltip.into_attribute_string(), "&lt;b title=&quot;hi&quot;&gt;x&lt;/b&gt;");
+    /// ```
+    pub fn into_attribute_string(self) -> String {
+        let mut escaped = String::new();
+        let _ = Escaper::new(&mut escaped).write_str(self.0.as_ref());
+        escaped
+    }
+
+    /// Converts the inner value to a reader over its bytes, for APIs
+    /// that take an `io::Read`.
+    pub fn into_reader(self) -> io::Cursor<Vec<u8>> {
+        io::Cursor::new(self.into_string().into_bytes())
+    }
+
+    /// Extracts the text of the markup, for plain-text email or search
+    /// indexes.
+    ///
+    /// Tags and comments are left out, along with the contents of
+    /// `script` and `style`, and escaped characters like `&amp;` are
+    /// decoded. No whitespace is added between elements.
+    ///
+    /// The text is found by re-reading the rendered HTML, so it follows
+    /// the same rules as a browser would. A `<` in `PreEscaped` content
+    /// stays in the text unless it's followed by a letter, `/`, `!` or
+    /// `?`, in which case it's read as the start of a tag and dropped.
+    /// Text spliced in the usual way is escaped and always kept.
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// use maud::html;
+    ///
+    /// let markup = html! { p { "Hello " b { "world" } " & friends" } };
+    /// assert_eq!(markup.into_text(), "Hello world & friends");
+    /// ```
+    pub fn into_text(self) -> String {
+        crate::pretty::text_content(self.0.as_ref())
+    }
+}
+
+impl<T: AsRef<str> + Into<String>> From<PreEscaped<T>> for String {
+    fn from(value: PreEscaped<T>) -> String {
+        value.into_string()
+    }
+}
+
+impl<T: AsRef<str>> Add<PreEscaped<T>> for Markup {
+    type Output = Markup;
+
+    /// Appends `other` to `self`, reusing the buffer of `self`.
+    fn add(mut self, other: PreEscaped<T>) -> Markup {
+        self += other;
+        self
+    }
+}
+
+impl<T: AsRef<str>> AddAssign<PreEscaped<T>> for Markup {
+    fn add_assign(&mut self, other: PreEscaped<T>) {
+        self.0.push_str(other.0.as_ref());
+    }
+}
+
+/// Returns a block of markup with no content.
+///
+/// This is the identity for `+` on markup.
+pub fn empty() -> Markup {
+    PreEscaped(String::new())
+}
+
+/// Renders each item in turn into a single block of markup.
+///
+/// # Example
+///
+/// ```rust
+/// use maud::{concat, html};
+///
+/// let fragments = vec![
+///     html! { li { "Applejack" } },
+///     html! { li { "Rarity" } },
+/// ];
+/// let markup = concat(fragments);
+/// assert_eq!(markup.into_string(), "<li>Applejack</li><li>Rarity</li>");
+/// ```
+pub fn concat<I>(items: I) -> Markup
+where
+    I: IntoIterator,
+    I::Item: Render,
+{
+    let mut buffer = String::new();
+    for item in items {
+        item.render_to(&mut buffer);
+    }
+    PreEscaped(buffer)
+}
+
+/// Collects items into markup, the same as [`concat`](fn.concat.html).
+///
+/// ```rust
+/// use maud::{html, Markup};
+///
+/// let names = vec!["Applejack", "Rarity"];
+/// let markup: Markup = names.iter().map(|name| html! { li { (name) } }).collect();
+/// assert_eq!(markup.into_string(), "<li>Applejack</li><li>Rarity</li>");
+/// ```
+impl<R: Render> FromIterator<R> for Markup {
+    fn from_iter<I: IntoIterator<Item = R>>(items: I) -> Markup {
+        concat(items)
+    }
+}
+
+/// Asserts that a block of markup renders to the expected HTML.
+///
+/// On failure, the panic message points at the first difference.
+///
+/// # Example
+///
+/// ```rust
+/// use maud::{assert_markup_eq, html};
+///
+/// assert_markup_eq!(html! { p { "Hi!" } }, "<p>Hi!</p>");
+/// ```
+#[macro_export]
+macro_rules! assert_markup_eq {
+    ($markup:expr, $expected:expr $(,)?) => {
+        $crate::assert_markup_eq_impl(&$markup, $expected)
+    };
 }
 
-impl<T: AsRef<str> + Into<String>> Into<String> for PreEscaped<T> {
-    fn into(self) -> String {
-        self.into_string()
+/// Defines a `#[test]` function that renders a template and checks it
+/// against the expected HTML, using
+/// [`assert_markup_eq!`](macro.assert_markup_eq.html).
+///
+/// Attributes before the name, like `#[should_panic]` or `#[ignore]`, are
+/// put on the test function.
+///
+/// # Example
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// maud::render_test!(greeting, {
+///     p { "Hello, " b { "world" } }
+/// }, "<p>Hello, <b>world</b></p>");
+/// ```
+#[macro_export]
+macro_rules! render_test {
+    ($(#[$attr:meta])* $name:ident, { $($body:tt)* }, $expected:expr $(,)?) => {
+        $(#[$attr])*
+        #[test]
+        fn $name() {
+            $crate::assert_markup_eq!($crate::html! { $($body)* }, $expected);
+        }
+    };
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_markup_eq_impl<T: Render + ?Sized>(markup: &T, expected: &str) {
+    let actual = markup.render().into_string();
+    if actual == expected {
+        return;
     }
+    let offset = actual
+        .char_indices()
+        .zip(expected.chars())
+        .find(|&((_, a), e)| a != e)
+        .map(|((i, _), _)| i)
+        .unwrap_or_else(|| actual.len().min(expected.len()));
+    let column = actual[..offset].chars().count();
+    panic!(
+        "markup differs from expected at byte {}\n  actual: `{}`\nexpected: `{}`\n{:width$}^",
+        offset, actual, expected, "", width = column + 11,
+    );
 }
 
-pub use maud_htmlescape::Escaper;
+pub use maud_htmlescape::{Escaper, EscapeChunks, escape_chunks};
 
 /// The literal string `<!DOCTYPE html>`.
 ///
@@ -149,6 +534,571 @@ pub use maud_htmlescape::Escaper;
 /// ```
 pub const DOCTYPE: PreEscaped<&'static str> = PreEscaped("<!DOCTYPE html>");
 
+/// Adapts an `io::Write` into a `fmt::Write`, for use with the
+/// `html!(writer, { ... })` form.
+///
+/// Since `fmt::Error` carries no information, the underlying I/O error is
+/// kept in the adapter instead.
+#[derive(Debug)]
+pub struct IoWriter<W: io::Write> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    /// Wraps the given writer.
+    pub fn new(inner: W) -> IoWriter<W> {
+        IoWriter { inner, error: None }
+    }
+
+    /// Returns the last I/O error, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Unwraps the writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Adapts a byte slice into a `fmt::Write`, so that a template can be
+/// rendered without allocating.
+///
+/// Each write either fits in the space left, or fails and leaves the
+/// slice as it was.
+#[derive(Debug)]
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    overflowed: bool,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Wraps the given buffer, starting at its beginning.
+    pub fn new(buf: &'a mut [u8]) -> SliceWriter<'a> {
+        SliceWriter { buf, len: 0, overflowed: false }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if nothing has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if a write didn't fit in the buffer.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Returns the part of the buffer that has been written.
+    pub fn as_str(&self) -> &str {
+        // Only whole `str`s are ever copied in
+        std::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl<'a> fmt::Write for SliceWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            self.overflowed = true;
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// The error from [`render_into_buf`](fn.render_into_buf.html) when the
+/// output doesn't fit in the buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("rendered markup doesn't fit in the buffer")
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+/// Renders a template into a byte buffer, and returns the number of bytes
+/// written.
+///
+/// The static parts of the template are copied straight into the buffer.
+/// Each splice is first rendered into a scratch `String`, which is taken
+/// from a per-thread pool. So once a thread has rendered a splice, later
+/// splices of a similar size don't allocate, but the first one does, as
+/// does any larger one.
+///
+/// If the template doesn't fit, this returns `Err(Overflow)`, and the
+/// contents of the buffer are unspecified. Errors from `@write` blocks are
+/// reported the same way.
+///
+/// # Example
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// use maud::html;
+///
+/// let mut buf = [0; 64];
+/// let len = maud::render_into_buf(|w| html!(w, { b { "Hello!" } }), &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"<b>Hello!</b>");
+/// ```
+pub fn render_into_buf<F>(render: F, buf: &mut [u8]) -> Result<usize, Overflow>
+where
+    F: FnOnce(&mut dyn fmt::Write) -> fmt::Result,
+{
+    let mut writer = SliceWriter::new(buf);
+    match render(&mut writer) {
+        Ok(()) => Ok(writer.len()),
+        Err(fmt::Error) => Err(Overflow),
+    }
+}
+
+/// Renders a template straight into a file, creating any missing parent
+/// directories.
+///
+/// The template is written through a buffer, without collecting the
+/// whole page in a `String` first.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use maud::html;
+///
+/// let title = "Ponyville";
+/// maud::render_to_file(|w| html!(w, { h1 { (title) } }), "site/index.html")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn render_to_file<F, P>(render: F, path: P) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn fmt::Write) -> fmt::Result,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = IoWriter::new(BufWriter::new(File::create(path)?));
+    if render(&mut writer).is_err() {
+        return Err(writer.take_error().unwrap_or_else(|| io::Error::other("failed to render template")));
+    }
+    io::Write::flush(&mut writer.into_inner())
+}
+
+/// Like [`render_to_file`](fn.render_to_file.html), but writes to a
+/// temporary file first and then renames it into place.
+///
+/// If rendering fails, the file at `path` is left untouched.
+pub fn render_to_path_atomic<F, P>(render: F, path: P) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn fmt::Write) -> fmt::Result,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    match render_to_file(render, &temp_path) {
+        Ok(()) => fs::rename(&temp_path, path),
+        Err(error) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(error)
+        },
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+pub mod reload;
+
+#[cfg(feature = "trace")]
+pub mod trace;
+
+#[doc(hidden)]
+pub mod rt {
+    use std::fmt::{self, Write};
+
+    use crate::{Escape, Escaper, Render};
+
+    pub use crate::pool::{SpliceBuffer, into_pooled, take_pooled};
+
+    /// Calls an `@extends` layout, with the blocks filled in by `f`.
+    pub fn extend<T, R, L, F>(layout: L, f: F) -> R
+    where
+        T: Default,
+        L: FnOnce(T) -> R,
+        F: FnOnce(&mut T),
+    {
+        let mut blocks = T::default();
+        f(&mut blocks);
+        layout(blocks)
+    }
+
+    /// Writes ` name="value"`, escaping the value the same way as an
+    /// attribute splice in `html!`.
+    ///
+    /// This is for `Render` impls that build tags by hand. The name is
+    /// written as is.
+    ///
+    /// ```rust
+    /// let mut s = String::new();
+    /// maud::rt::write_attr(&mut s, "title", "a\"b");
+    /// assert_eq!(s, r#" title="a&quot;b""#);
+    /// ```
+    pub fn write_attr<T: Render + ?Sized>(buffer: &mut String, name: &str, value: &T) {
+        buffer.push(' ');
+        buffer.push_str(name);
+        buffer.push_str("=\"");
+        value.render_to(buffer);
+        buffer.push('"');
+    }
+
+    /// Runs `f`, or returns `None` if it panics, for `(value ?? fallback)`
+    /// splices.
+    pub fn catch_panic<T, F: FnOnce() -> T>(f: F) -> Option<T> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).ok()
+    }
+
+    /// Writes at most `limit` characters of `s`, escaped, followed by `…`
+    /// if anything was cut off.
+    ///
+    /// ```rust
+    /// let mut s = String::new();
+    /// maud::rt::write_truncated(&mut s, "<ponies>", 4);
+    /// assert_eq!(s, "&lt;pon…");
+    /// ```
+    pub fn write_truncated(w: &mut String, s: &str, limit: usize) {
+        let (head, ellipsis) = truncate(s, limit);
+        let _ = Escaper::new(w).write_str(head);
+        w.push_str(ellipsis);
+    }
+
+    /// Splits off the first `limit` characters of `s`, and returns them
+    /// with the ellipsis to write after them.
+    fn truncate(s: &str, limit: usize) -> (&str, &'static str) {
+        match s.char_indices().nth(limit) {
+            Some((end, _)) => (&s[..end], "…"),
+            None => (s, ""),
+        }
+    }
+
+    /// The value of a `@truncate limit (value)` splice.
+    pub struct Truncated<T: fmt::Display>(pub T, pub usize);
+
+    impl<T: fmt::Display> Render for Truncated<T> {
+        fn render_to(&self, w: &mut String) {
+            write_truncated(w, &self.0.to_string(), self.1);
+        }
+
+        fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+            let text = self.0.to_string();
+            let (head, ellipsis) = truncate(&text, self.1);
+            escape.escape_to(head, w);
+            escape.escape_to(ellipsis, w);
+        }
+    }
+
+    /// Collects the key-value pairs of a `@for_sorted` loop, sorted by key.
+    pub fn sorted_by_key<K: Ord, V, I: IntoIterator<Item = (K, V)>>(pairs: I) -> Vec<(K, V)> {
+        let mut pairs = pairs.into_iter().collect::<Vec<_>>();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        pairs
+    }
+
+    /// A class list built at runtime, which puts exactly one space between
+    /// classes.
+    #[derive(Default)]
+    pub struct ClassList(SpliceBuffer);
+
+    impl ClassList {
+        pub fn new() -> ClassList {
+            ClassList::default()
+        }
+
+        /// Adds a class, unless it renders to nothing.
+        pub fn push<T: Render + ?Sized>(&mut self, class: &T) {
+            self.push_with(|w| class.render_to(w));
+        }
+
+        /// Adds a class escaped with `escape`, for `escaper = ...`.
+        pub fn push_escaped<T: Render + ?Sized>(&mut self, class: &T, escape: &dyn Escape) {
+            self.push_with(|w| class.render_escaped_to(w, escape));
+        }
+
+        fn push_with(&mut self, render: impl FnOnce(&mut String)) {
+            let before = self.0.len();
+            if before > 0 {
+                self.0.push(' ');
+            }
+            let start = self.0.len();
+            render(&mut self.0);
+            if self.0.len() == start {
+                self.0.truncate(before);
+            }
+        }
+    }
+
+    impl Render for ClassList {
+        fn render_to(&self, w: &mut String) {
+            w.push_str(&self.0);
+        }
+
+        fn render_escaped_to(&self, w: &mut String, _: &dyn Escape) {
+            w.push_str(&self.0);
+        }
+    }
+
+    /// The attributes of a `@defaults(pairs)`, leaving out any that the
+    /// element sets itself or that come up again later in `pairs`.
+    pub struct Defaults<K, V> {
+        pairs: Vec<(K, V)>,
+        skip: &'static [&'static str],
+    }
+
+    impl<K: AsRef<str>, V: Render> Defaults<K, V> {
+        pub fn new<I: IntoIterator<Item = (K, V)>>(pairs: I, skip: &'static [&'static str]) -> Defaults<K, V> {
+            Defaults { pairs: pairs.into_iter().collect(), skip }
+        }
+
+        /// Calls `f` with each attribute that should be written.
+        fn each(&self, mut f: impl FnMut(&str, &V)) {
+            for (i, (name, value)) in self.pairs.iter().enumerate() {
+                let name = name.as_ref();
+                let overridden = self.skip.iter().any(|other| other.eq_ignore_ascii_case(name))
+                    || self.pairs[i + 1..].iter().any(|(other, _)| other.as_ref().eq_ignore_ascii_case(name));
+                // Names that could break out of the tag are dropped
+                let invalid = |c: char| c.is_whitespace() || c.is_control() || "\"'<>/=".contains(c);
+                if !overridden && !name.is_empty() && !name.contains(invalid) {
+                    f(name, value);
+                }
+            }
+        }
+
+        fn write(&self, w: &mut String, escape: Option<&dyn Escape>) {
+            self.each(|name, value| write_default(w, name, value, escape));
+        }
+    }
+
+    fn write_default<V: Render>(w: &mut String, name: &str, value: &V, escape: Option<&dyn Escape>) {
+        w.push(' ');
+        w.push_str(name);
+        w.push_str("=\"");
+        match escape {
+            Some(escape) => value.render_escaped_to(w, escape),
+            None => value.render_to(w),
+        }
+        w.push('"');
+    }
+
+    impl<K: AsRef<str>, V: Render> Render for Defaults<K, V> {
+        fn render_to(&self, w: &mut String) {
+            self.write(w, None);
+        }
+
+        fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+            self.write(w, Some(escape));
+        }
+    }
+
+    /// The attributes of an element with a `@defaults` under `sort_attrs`.
+    /// The names of the defaults aren't known until runtime, so they're
+    /// sorted in with the element's own attributes here.
+    #[derive(Default)]
+    pub struct SortedAttrs(Vec<(String, String)>);
+
+    impl SortedAttrs {
+        /// Adds an attribute that's already been written out, like
+        /// ` title="Hi"`.
+        pub fn push(&mut self, name: &str, rendered: String) {
+            let key = name.to_ascii_lowercase();
+            // Equal names keep the order they were added in
+            let index = self.0.partition_point(|(other, _)| *other <= key);
+            self.0.insert(index, (key, rendered));
+        }
+
+        /// Adds the attributes of a `@defaults(pairs)`.
+        pub fn defaults<K: AsRef<str>, V: Render>(&mut self, defaults: Defaults<K, V>, escape: Option<&dyn Escape>) {
+            defaults.each(|name, value| {
+                let mut rendered = String::new();
+                write_default(&mut rendered, name, value, escape);
+                self.push(name, rendered);
+            });
+        }
+    }
+
+    impl Render for SortedAttrs {
+        fn render_to(&self, w: &mut String) {
+            for (_, rendered) in &self.0 {
+                w.push_str(rendered);
+            }
+        }
+
+        fn render_escaped_to(&self, w: &mut String, _: &dyn Escape) {
+            self.render_to(w);
+        }
+    }
+
+    /// The value of a `srcset=(value)` splice.
+    ///
+    /// The generated code calls `(&SrcSet(&value)).srcset()`. A list of
+    /// pairs picks up `SrcSetPairs` first, and anything else that
+    /// implements `Render` falls back to `SrcSetRender` through autoref.
+    pub struct SrcSet<'a, T: ?Sized>(pub &'a T);
+
+    /// Joins a list of `(url, descriptor)` pairs with commas.
+    pub trait SrcSetPairs {
+        type Output: Render;
+        fn srcset(&self) -> Self::Output;
+    }
+
+    impl<'a, T: SrcSetValue + ?Sized> SrcSetPairs for SrcSet<'a, T> {
+        type Output = SrcSetList<'a, T>;
+        fn srcset(&self) -> SrcSetList<'a, T> {
+            SrcSetList(self.0)
+        }
+    }
+
+    /// Renders any other value as usual.
+    pub trait SrcSetRender {
+        type Output: Render;
+        fn srcset(&self) -> Self::Output;
+    }
+
+    impl<'a, T: Render + ?Sized> SrcSetRender for &SrcSet<'a, T> {
+        type Output = SrcSetText<'a, T>;
+        fn srcset(&self) -> SrcSetText<'a, T> {
+            SrcSetText(self.0)
+        }
+    }
+
+    /// A list of pairs, ready to render.
+    pub struct SrcSetList<'a, T: ?Sized>(&'a T);
+
+    impl<'a, T: SrcSetValue + ?Sized> Render for SrcSetList<'a, T> {
+        fn render_to(&self, w: &mut String) {
+            self.0.write_srcset(w, None);
+        }
+
+        fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+            self.0.write_srcset(w, Some(escape));
+        }
+    }
+
+    /// Any other value, ready to render.
+    pub struct SrcSetText<'a, T: ?Sized>(&'a T);
+
+    impl<'a, T: Render + ?Sized> Render for SrcSetText<'a, T> {
+        fn render_to(&self, w: &mut String) {
+            self.0.render_to(w);
+        }
+
+        fn render_escaped_to(&self, w: &mut String, escape: &dyn Escape) {
+            self.0.render_escaped_to(w, escape);
+        }
+    }
+
+    /// A list of `(url, descriptor)` pairs that can be spliced into
+    /// `srcset`.
+    pub trait SrcSetValue {
+        fn write_srcset(&self, w: &mut String, escape: Option<&dyn Escape>);
+    }
+
+    fn render_with<T: Render + ?Sized>(value: &T, w: &mut String, escape: Option<&dyn Escape>) {
+        match escape {
+            Some(escape) => value.render_escaped_to(w, escape),
+            None => value.render_to(w),
+        }
+    }
+
+    impl<A: Render, B: Render> SrcSetValue for [(A, B)] {
+        fn write_srcset(&self, w: &mut String, escape: Option<&dyn Escape>) {
+            for (i, (url, descriptor)) in self.iter().enumerate() {
+                if i > 0 {
+                    w.push_str(", ");
+                }
+                render_with(url, w, escape);
+                let start = w.len();
+                w.push(' ');
+                render_with(descriptor, w, escape);
+                // Leave out the space if there's no descriptor
+                if w.len() == start + 1 {
+                    w.truncate(start);
+                }
+            }
+        }
+    }
+
+    impl<A: Render, B: Render, const N: usize> SrcSetValue for [(A, B); N] {
+        fn write_srcset(&self, w: &mut String, escape: Option<&dyn Escape>) {
+            self[..].write_srcset(w, escape);
+        }
+    }
+
+    impl<A: Render, B: Render> SrcSetValue for Vec<(A, B)> {
+        fn write_srcset(&self, w: &mut String, escape: Option<&dyn Escape>) {
+            self[..].write_srcset(w, escape);
+        }
+    }
+
+    impl<T: SrcSetValue + ?Sized> SrcSetValue for &T {
+        fn write_srcset(&self, w: &mut String, escape: Option<&dyn Escape>) {
+            (**self).write_srcset(w, escape);
+        }
+    }
+
+    /// A `(name, enabled)` pair in `class=toggle[flags]`.
+    pub trait ClassFlag<'a> {
+        /// Returns the class name if it's enabled.
+        fn class_flag(self) -> Option<&'a str>;
+    }
+
+    impl<'a, S: AsRef<str>> ClassFlag<'a> for &'a (S, bool) {
+        fn class_flag(self) -> Option<&'a str> {
+            if self.1 { Some(self.0.as_ref()) } else { None }
+        }
+    }
+
+    impl<'a, S: AsRef<str> + ?Sized> ClassFlag<'a> for (&'a S, bool) {
+        fn class_flag(self) -> Option<&'a str> {
+            if self.1 { Some(self.0.as_ref()) } else { None }
+        }
+    }
+
+    impl<'a, S: AsRef<str> + ?Sized> ClassFlag<'a> for (&'a S, &'a bool) {
+        fn class_flag(self) -> Option<&'a str> {
+            if *self.1 { Some(self.0.as_ref()) } else { None }
+        }
+    }
+
+    /// Yields the names of the enabled classes in `class=toggle[flags]`.
+    pub fn class_flags<'a, I>(flags: I) -> impl Iterator<Item = &'a str>
+    where
+        I: IntoIterator,
+        I::Item: ClassFlag<'a>,
+    {
+        flags.into_iter().filter_map(ClassFlag::class_flag)
+    }
+}
+
 #[cfg(feature = "iron")]
 mod iron_support {
     use std::io;
@@ -191,6 +1141,19 @@ mod rocket_support {
     }
 }
 
+#[cfg(feature = "axum")]
+mod axum_support {
+    use crate::PreEscaped;
+    use axum_core::response::{IntoResponse, Response};
+    use http::header;
+
+    impl IntoResponse for PreEscaped<String> {
+        fn into_response(self) -> Response {
+            ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], self.0).into_response()
+        }
+    }
+}
+
 #[cfg(feature = "actix-web")]
 mod actix_support {
     use crate::PreEscaped;
@@ -0,0 +1,330 @@
+//! A tree view of rendered markup, for rewriting it after the fact.
+//!
+//! This is a best-effort re-parser for the markup that `html!` writes,
+//! built on the same tokenizer as the pretty printer. It isn't an HTML
+//! parser, and doesn't follow the HTML spec's error recovery.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::pretty::{self, Token};
+use crate::{Escaper, Markup, PreEscaped, Render};
+
+/// A piece of rendered markup.
+///
+/// Build these from a `Markup` with
+/// [`PreEscaped::to_nodes`](struct.PreEscaped.html#method.to_nodes), and
+/// turn them back into markup with `Render`.
+///
+/// The tree is read back from the rendered string, so it doesn't know
+/// which parts of the text came from splices. Literal text and spliced
+/// values that end up next to each other are one `Text` node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    Element(Element),
+    /// Text, as it was written, so any special characters are already
+    /// escaped.
+    Text(String),
+    /// A comment, doctype, or stray tag, which is copied as is.
+    Raw(String),
+}
+
+/// An element and its contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    /// The attributes in the order they were written, with their values
+    /// unescaped. Empty attributes have no value.
+    pub attrs: Vec<(String, Option<String>)>,
+    /// The contents of the element, or `None` for a void element like
+    /// `br`.
+    pub children: Option<Vec<Node>>,
+    /// Whether a void element is written as `<br />`.
+    pub self_closing: bool,
+}
+
+impl Node {
+    /// Calls `f` on this node and everything inside it, parents first.
+    pub fn walk_mut<F: FnMut(&mut Node)>(&mut self, f: &mut F) {
+        f(self);
+        if let Node::Element(Element { children: Some(ref mut children), .. }) = *self {
+            for child in children {
+                child.walk_mut(f);
+            }
+        }
+    }
+}
+
+impl Element {
+    /// Returns the value of an attribute, or `Some("")` if it's empty.
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(other, _)| other.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_deref().unwrap_or(""))
+    }
+
+    /// Sets an attribute, replacing it if it's already there.
+    pub fn set_attr(&mut self, name: &str, value: &str) {
+        match self.attrs.iter_mut().find(|(other, _)| other.eq_ignore_ascii_case(name)) {
+            Some(attr) => attr.1 = Some(value.to_string()),
+            None => self.attrs.push((name.to_string(), Some(value.to_string()))),
+        }
+    }
+
+    /// Parses the name and attributes out of an opening tag.
+    fn parse(name: &str, tag: &str, children: Option<Vec<Node>>) -> Element {
+        let mut attrs = Vec::new();
+        let mut rest = tag[1 + name.len()..].trim_end_matches('>');
+        // A `/` at the end is part of an unquoted value, as in `href=/`,
+        // unless there's a space or quote before it
+        let self_closing = rest.strip_suffix('/').is_some_and(|before| {
+            before.is_empty() || before.ends_with(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+        });
+        if self_closing {
+            rest = &rest[..rest.len() - 1];
+        }
+        loop {
+            rest = rest.trim_start();
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '=')
+                .unwrap_or(rest.len());
+            if end == 0 {
+                break;
+            }
+            let attr_name = rest[..end].to_string();
+            rest = rest[end..].trim_start();
+            let value = match rest.strip_prefix('=') {
+                Some(value) => {
+                    let value = value.trim_start();
+                    let (raw, after) = match value.chars().next() {
+                        Some(quote) if quote == '"' || quote == '\'' => {
+                            let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                            (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                        },
+                        _ => {
+                            let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                            (&value[..end], &value[end..])
+                        },
+                    };
+                    rest = after;
+                    let mut unescaped = String::new();
+                    pretty::unescape(&mut unescaped, raw);
+                    Some(unescaped)
+                },
+                None => None,
+            };
+            attrs.push((attr_name, value));
+        }
+        Element { name: name.to_string(), attrs, children, self_closing }
+    }
+}
+
+impl Render for Node {
+    fn render_to(&self, w: &mut String) {
+        match *self {
+            Node::Element(ref element) => element.render_to(w),
+            Node::Text(ref text) | Node::Raw(ref text) => w.push_str(text),
+        }
+    }
+}
+
+impl Render for Element {
+    fn render_to(&self, w: &mut String) {
+        w.push('<');
+        w.push_str(&self.name);
+        for (name, value) in &self.attrs {
+            w.push(' ');
+            w.push_str(name);
+            if let Some(ref value) = *value {
+                w.push_str("=\"");
+                Escaper::new(w).write_str(value).unwrap();
+                w.push('"');
+            }
+        }
+        match self.children {
+            Some(ref children) => {
+                w.push('>');
+                for child in children {
+                    child.render_to(w);
+                }
+                w.push_str("</");
+                w.push_str(&self.name);
+                w.push('>');
+            },
+            None if self.self_closing => w.push_str(" />"),
+            None => w.push('>'),
+        }
+    }
+}
+
+/// Builds a tree out of rendered markup.
+///
+/// Closing tags that don't match any open element are kept as `Raw`
+/// nodes, and elements that are never closed end with the input.
+pub(crate) fn parse(html: &str) -> Vec<Node> {
+    let mut root = Vec::new();
+    let mut open: Vec<Element> = Vec::new();
+    for token in pretty::tokenize(html) {
+        let node = match token {
+            Token::Open { name, tag } => {
+                open.push(Element::parse(name, tag, Some(Vec::new())));
+                continue;
+            },
+            Token::Close { name, tag } => {
+                match open.iter().rposition(|element| element.name.eq_ignore_ascii_case(name)) {
+                    Some(depth) => {
+                        // Close anything left open inside it first
+                        while open.len() > depth + 1 {
+                            close(&mut open, &mut root);
+                        }
+                        close(&mut open, &mut root);
+                        continue;
+                    },
+                    None => Node::Raw(tag.to_string()),
+                }
+            },
+            Token::Void { tag, .. } if tag.starts_with("<!") || tag.starts_with("<?") => {
+                Node::Raw(tag.to_string())
+            },
+            Token::Void { name, tag } => Node::Element(Element::parse(name, tag, None)),
+            Token::Text(text) => Node::Text(text.to_string()),
+            Token::Unterminated(text) => Node::Raw(text.to_string()),
+        };
+        push(node, &mut open, &mut root);
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut root);
+    }
+    root
+}
+
+/// Adds a node to the innermost open element.
+fn push(node: Node, open: &mut [Element], root: &mut Vec<Node>) {
+    match open.last_mut() {
+        Some(Element { children: Some(ref mut children), .. }) => children.push(node),
+        _ => root.push(node),
+    }
+}
+
+/// Closes the innermost open element.
+fn close(open: &mut Vec<Element>, root: &mut Vec<Node>) {
+    if let Some(element) = open.pop() {
+        push(Node::Element(element), open, root);
+    }
+}
+
+impl<T: AsRef<str>> PreEscaped<T> {
+    /// Parses the markup into a tree of [`Node`](enum.Node.html)s, which
+    /// can be changed and then rendered again.
+    ///
+    /// This re-parses the rendered string, so it costs more than the
+    /// rendering did. It's meant for markup written by `html!`, and isn't
+    /// a full HTML parser: `PreEscaped` text with a stray `<`, for
+    /// example, may be read as a tag.
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// use maud::{html, Markup, Node};
+    ///
+    /// let page = html! { a href="/ponies" { "Ponies" } };
+    /// let mut nodes = page.to_nodes();
+    /// for node in &mut nodes {
+    ///     node.walk_mut(&mut |node| {
+    ///         if let Node::Element(element) = node {
+    ///             if element.name == "a" {
+    ///                 element.set_attr("rel", "nofollow");
+    ///             }
+    ///         }
+    ///     });
+    /// }
+    /// let page: Markup = nodes.into_iter().collect();
+    /// assert_eq!(page.into_string(), r#"<a href="/ponies" rel="nofollow">Ponies</a>"#);
+    /// ```
+    pub fn to_nodes(&self) -> Vec<Node> {
+        parse(self.0.as_ref())
+    }
+
+    /// Passes the text in the markup through `f`, leaving the tags as they
+    /// are.
+    ///
+    /// `f` gets the text unescaped, and what it returns is escaped again.
+    /// The contents of `script` and `style`, and comments, are left out,
+    /// as are attributes. To change the text in attributes as well, use
+    /// [`map_text_with_attrs`](#method.map_text_with_attrs).
+    ///
+    /// Like `to_nodes`, this works on the rendered string, so literal text
+    /// and spliced values that sit side by side reach `f` as one piece.
+    /// `p { "Hello, " (name) }` calls `f` once, with `"Hello, Rarity"`.
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// use maud::html;
+    ///
+    /// let markup = html! { p title="hi" { "Hello, " b { "world" } } };
+    /// let shouted = markup.map_text(|text| text.to_uppercase().into());
+    /// assert_eq!(shouted.into_string(), r#"<p title="hi">HELLO, <b>WORLD</b></p>"#);
+    /// ```
+    pub fn map_text<F: FnMut(&str) -> Cow<'_, str>>(&self, mut f: F) -> Markup {
+        let mut nodes = self.to_nodes();
+        map_text(&mut nodes, false, &mut f);
+        nodes.into_iter().collect()
+    }
+
+    /// Like [`map_text`](#method.map_text), but also passes through `f`
+    /// the values of attributes that hold text for people to read: `alt`,
+    /// `title`, `placeholder`, `label`, and `aria-label`.
+    ///
+    /// Other attributes, like `href` and `class`, are never changed; use
+    /// [`to_nodes`](#method.to_nodes) for those.
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// use maud::html;
+    ///
+    /// let markup = html! { p title="hi" { "Hello, " b { "world" } } };
+    /// let shouted = markup.map_text_with_attrs(|text| text.to_uppercase().into());
+    /// assert_eq!(shouted.into_string(), r#"<p title="HI">HELLO, <b>WORLD</b></p>"#);
+    /// ```
+    pub fn map_text_with_attrs<F: FnMut(&str) -> Cow<'_, str>>(&self, mut f: F) -> Markup {
+        let mut nodes = self.to_nodes();
+        map_text(&mut nodes, true, &mut f);
+        nodes.into_iter().collect()
+    }
+}
+
+/// Attributes whose values are text for people to read.
+const TEXT_ATTRS: &[&str] = &["alt", "title", "placeholder", "label", "aria-label"];
+
+fn map_text<F: FnMut(&str) -> Cow<'_, str>>(nodes: &mut [Node], include_attrs: bool, f: &mut F) {
+    for node in nodes {
+        match *node {
+            Node::Text(ref mut text) => {
+                let mut unescaped = String::new();
+                pretty::unescape(&mut unescaped, text);
+                let mut escaped = String::new();
+                Escaper::new(&mut escaped).write_str(&f(&unescaped)).unwrap();
+                *text = escaped;
+            },
+            Node::Element(ref mut element) => {
+                if include_attrs {
+                    for (name, value) in &mut element.attrs {
+                        if let Some(ref mut value) = *value {
+                            if TEXT_ATTRS.iter().any(|attr| name.eq_ignore_ascii_case(attr)) {
+                                // Attribute values are kept unescaped
+                                *value = f(value).into_owned();
+                            }
+                        }
+                    }
+                }
+                let is_raw = element.name.eq_ignore_ascii_case("script") || element.name.eq_ignore_ascii_case("style");
+                if let Some(ref mut children) = element.children {
+                    if !is_raw {
+                        map_text(children, include_attrs, f);
+                    }
+                }
+            },
+            Node::Raw(_) => {},
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Thread-local pools of `String` buffers, so that rendering a page
+//! doesn't have to allocate from scratch every time.
+//!
+//! Templates written with `pooled = true` render into a [`PooledHtml`],
+//! which hands its buffer back to the pool when it's dropped. Splices in
+//! writer templates borrow a [`SpliceBuffer`] from a second, smaller pool.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::thread::LocalKey;
+
+use crate::{Escape, Render};
+
+/// The most buffers that each thread keeps for reuse, in each pool.
+const MAX_POOLED_BUFFERS: usize = 8;
+
+/// Page buffers larger than this are freed rather than kept, so that one
+/// huge page doesn't tie up its memory forever.
+const MAX_POOLED_CAPACITY: usize = 1 << 20;
+
+/// Splice buffers only ever hold one value at a time, so they're kept to
+/// a smaller size.
+const MAX_SPLICE_CAPACITY: usize = 64 << 10;
+
+type Pool = LocalKey<RefCell<Vec<String>>>;
+
+thread_local! {
+    /// Buffers for whole pages.
+    static PAGES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    /// Scratch buffers for splices and class lists, kept apart so that
+    /// they don't take page-sized buffers from `PAGES`.
+    static SPLICES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Takes an empty buffer from a pool.
+///
+/// This picks the smallest pooled buffer that can hold `capacity` bytes,
+/// or the largest one if none can, and reserves the rest.
+fn take(pool: &'static Pool, capacity: usize) -> String {
+    let buffer = pool.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let best = pool.iter()
+            .enumerate()
+            .min_by_key(|&(_, buffer)| {
+                let fits = buffer.capacity() >= capacity;
+                // Prefer buffers that fit, then the tightest fit, then the
+                // biggest of those that don't
+                (!fits, if fits { buffer.capacity() } else { usize::MAX - buffer.capacity() })
+            })
+            .map(|(i, _)| i);
+        best.map(|i| pool.swap_remove(i))
+    });
+    let mut buffer = buffer.unwrap_or_default();
+    buffer.reserve(capacity);
+    buffer
+}
+
+/// Puts a buffer back in a pool, unless it's empty or bigger than
+/// `max_capacity`.
+fn give_back(pool: &'static Pool, mut buffer: String, max_capacity: usize) {
+    if buffer.capacity() == 0 || buffer.capacity() > max_capacity {
+        return;
+    }
+    buffer.clear();
+    // The pool may be gone if this is dropped while the thread exits
+    let _ = pool.try_with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() < MAX_POOLED_BUFFERS {
+            pool.push(buffer);
+        }
+    });
+}
+
+/// A rendered page in a buffer borrowed from a per-thread pool.
+///
+/// Dereferences to the rendered `str`. When dropped, the buffer goes back
+/// to the pool of the thread that drops it, so that the next page can be
+/// rendered without allocating.
+///
+/// The `pooled = true` option makes `html!` render straight into one,
+/// reserving as much as the template is known to need:
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// use maud::{html, PooledHtml};
+///
+/// let page: PooledHtml = html!(pooled = true, { p { "Hello!" } });
+/// assert_eq!(&*page, "<p>Hello!</p>");
+/// ```
+///
+/// It also implements `fmt::Write`, so a template can be written into it
+/// with the writer form of `html!`:
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// use maud::{html, PooledHtml};
+///
+/// let mut page = PooledHtml::with_capacity(4096);
+/// html!(&mut page, { p { "Hello!" } }).unwrap();
+/// assert_eq!(&*page, "<p>Hello!</p>");
+/// ```
+pub struct PooledHtml {
+    buffer: String,
+}
+
+impl PooledHtml {
+    /// Takes an empty buffer from the pool.
+    ///
+    /// This picks the smallest pooled buffer that can hold `capacity`
+    /// bytes, or the largest one if none can, and reserves the rest.
+    pub fn with_capacity(capacity: usize) -> PooledHtml {
+        PooledHtml { buffer: take(&PAGES, capacity) }
+    }
+
+    /// Returns the number of bytes the buffer can hold without
+    /// reallocating.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Moves the rendered text out, leaving the buffer out of the pool.
+    pub fn into_string(mut self) -> String {
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+impl Drop for PooledHtml {
+    fn drop(&mut self) {
+        give_back(&PAGES, std::mem::take(&mut self.buffer), MAX_POOLED_CAPACITY);
+    }
+}
+
+impl From<PooledHtml> for String {
+    fn from(html: PooledHtml) -> String {
+        html.into_string()
+    }
+}
+
+impl Deref for PooledHtml {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.buffer
+    }
+}
+
+impl AsRef<str> for PooledHtml {
+    fn as_ref(&self) -> &str {
+        &self.buffer
+    }
+}
+
+impl fmt::Write for PooledHtml {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buffer.push_str(s);
+        Ok(())
+    }
+}
+
+impl fmt::Display for PooledHtml {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.buffer)
+    }
+}
+
+/// Splices the text as is, since it's already markup.
+impl Render for PooledHtml {
+    fn render_to(&self, w: &mut String) {
+        w.push_str(&self.buffer);
+    }
+
+    fn render_escaped_to(&self, w: &mut String, _: &dyn Escape) {
+        w.push_str(&self.buffer);
+    }
+}
+
+impl fmt::Debug for PooledHtml {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.buffer, f)
+    }
+}
+
+/// Renders a value into a buffer from the per-thread pool, reserving at
+/// least `capacity` bytes.
+///
+/// This is for `Render` types that write themselves out with
+/// `render_to`. A `Markup` has already been rendered into its own
+/// `String`, so this only copies it; use `html!(pooled = true, { ... })`
+/// to render a template into a pooled buffer in the first place. See
+/// [`PooledHtml`](struct.PooledHtml.html).
+pub fn render_pooled<T: Render + ?Sized>(value: &T, capacity: usize) -> PooledHtml {
+    let mut html = PooledHtml::with_capacity(capacity);
+    value.render_to(&mut html.buffer);
+    html
+}
+
+/// Takes a buffer for `html!(pooled = true, { ... })` to render into.
+#[doc(hidden)]
+pub fn take_pooled(capacity: usize) -> String {
+    take(&PAGES, capacity)
+}
+
+/// Wraps up a buffer from `take_pooled`.
+#[doc(hidden)]
+pub fn into_pooled(buffer: String) -> PooledHtml {
+    PooledHtml { buffer }
+}
+
+/// The buffer that `html!(writer, { ... })` renders each splice into
+/// before writing it out, borrowed from a pool of its own.
+#[doc(hidden)]
+pub struct SpliceBuffer {
+    buffer: String,
+}
+
+impl SpliceBuffer {
+    pub fn new() -> SpliceBuffer {
+        SpliceBuffer { buffer: take(&SPLICES, 0) }
+    }
+}
+
+impl Default for SpliceBuffer {
+    fn default() -> SpliceBuffer {
+        SpliceBuffer::new()
+    }
+}
+
+impl Drop for SpliceBuffer {
+    fn drop(&mut self) {
+        give_back(&SPLICES, std::mem::take(&mut self.buffer), MAX_SPLICE_CAPACITY);
+    }
+}
+
+impl Deref for SpliceBuffer {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.buffer
+    }
+}
+
+impl DerefMut for SpliceBuffer {
+    fn deref_mut(&mut self) -> &mut String {
+        &mut self.buffer
+    }
+}
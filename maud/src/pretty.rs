@@ -0,0 +1,349 @@
+use std::fmt;
+
+use crate::PreEscaped;
+
+/// Elements whose contents are copied as is, since whitespace matters in
+/// them or they aren't HTML.
+const PREFORMATTED: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// Elements that flow with the text around them, rather than starting a
+/// new line.
+const INLINE: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "br", "button", "cite", "code", "data", "del", "dfn", "em",
+    "i", "img", "input", "ins", "kbd", "label", "mark", "meter", "output", "progress", "q", "s",
+    "samp", "select", "small", "span", "strong", "sub", "sup", "time", "u", "var", "wbr",
+];
+
+/// Elements that never have a closing tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+    "source", "track", "wbr",
+];
+
+#[derive(Clone, Copy)]
+pub(crate) enum Token<'a> {
+    Open { name: &'a str, tag: &'a str },
+    Close { name: &'a str, tag: &'a str },
+    /// A void element, comment, doctype, or processing instruction.
+    Void { name: &'a str, tag: &'a str },
+    Text(&'a str),
+    /// A tag or comment that runs off the end of the input.
+    Unterminated(&'a str),
+}
+
+/// Splits rendered markup into tags and text.
+pub(crate) fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        if !starts_tag(rest) {
+            let end = rest
+                .match_indices('<')
+                .map(|(i, _)| i)
+                .find(|&i| starts_tag(&rest[i..]))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Text(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| end + 3)
+        } else {
+            tag_end(rest)
+        };
+        let end = match end {
+            Some(end) => end,
+            None => {
+                tokens.push(Token::Unterminated(rest));
+                break;
+            },
+        };
+        let tag = &rest[..end];
+        rest = &rest[end..];
+        let name_start = if tag.starts_with("</") { 2 } else { 1 };
+        let name_end = tag[name_start..]
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .map_or(tag.len(), |end| end + name_start);
+        let name = &tag[name_start..name_end];
+        tokens.push(if tag.starts_with("</") {
+            Token::Close { name, tag }
+        } else if tag.starts_with("<!") || tag.starts_with("<?") || tag.ends_with("/>") || is_one_of(name, VOID) {
+            Token::Void { name, tag }
+        } else {
+            Token::Open { name, tag }
+        });
+        // Don't look for tags inside `script` and friends
+        if let Some(&Token::Open { name, .. }) = tokens.last() {
+            if is_one_of(name, PREFORMATTED) {
+                let close = format!("</{}", name.to_ascii_lowercase());
+                let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                if end > 0 {
+                    tokens.push(Token::Text(&rest[..end]));
+                }
+                rest = &rest[end..];
+            }
+        }
+    }
+    tokens
+}
+
+/// Checks whether `html` starts with a tag or comment, rather than a `<`
+/// that browsers would show as text, like the one in `1 < 2`.
+fn starts_tag(html: &str) -> bool {
+    let mut chars = html.chars();
+    if chars.next() != Some('<') {
+        return false;
+    }
+    match chars.next() {
+        Some('/') => chars.next().is_some_and(|c| c.is_ascii_alphabetic()),
+        Some(c) => c.is_ascii_alphabetic() || c == '!' || c == '?',
+        None => false,
+    }
+}
+
+/// Finds the end of the tag at the start of `html`, skipping over quoted
+/// attribute values.
+fn tag_end(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {},
+        }
+    }
+    None
+}
+
+fn is_one_of(name: &str, names: &[&str]) -> bool {
+    names.iter().any(|other| name.eq_ignore_ascii_case(other))
+}
+
+/// Writes `line` on its own line, indented by `indent` spaces.
+fn line<W: fmt::Write + ?Sized>(w: &mut W, indent: usize, line: &str) -> fmt::Result {
+    writeln!(w, "{:indent$}{}", "", line, indent = indent)
+}
+
+/// Checks that every tag is closed in the right order, and that no tag
+/// or quoted value runs off the end.
+///
+/// This is nowhere near a full HTML parser, but it catches the usual ways
+/// that pre-escaped text can break a page.
+pub(crate) fn check_well_formed(html: &str) -> Result<(), String> {
+    let mut open = Vec::new();
+    for token in tokenize(html) {
+        match token {
+            Token::Unterminated(tag) => return Err(format!("unterminated tag `{}`", tag)),
+            Token::Open { name, .. } => open.push(name),
+            Token::Close { name, tag } => match open.pop() {
+                Some(expected) if expected.eq_ignore_ascii_case(name) => {},
+                Some(expected) => {
+                    return Err(format!("found `{}` where `</{}>` was expected", tag, expected));
+                },
+                None => return Err(format!("found `{}` with no matching open tag", tag)),
+            },
+            Token::Void { .. } | Token::Text(_) => {},
+        }
+    }
+    match open.pop() {
+        Some(name) => Err(format!("`<{}>` is never closed", name)),
+        None => Ok(()),
+    }
+}
+
+/// Collects the text in rendered markup, leaving out tags, comments, and
+/// the contents of `script` and `style`, and decoding character
+/// references.
+pub(crate) fn text_content(html: &str) -> String {
+    let mut text = String::new();
+    let mut hidden = 0usize;
+    for token in tokenize(html) {
+        match token {
+            Token::Open { name, .. } if is_one_of(name, &["script", "style"]) => hidden += 1,
+            Token::Close { name, .. } if is_one_of(name, &["script", "style"]) => {
+                hidden = hidden.saturating_sub(1);
+            },
+            Token::Text(chunk) if hidden == 0 => unescape(&mut text, chunk),
+            _ => {},
+        }
+    }
+    text
+}
+
+/// Appends `escaped` to `text`, decoding the character references that
+/// escaping produces, like `&amp;` and `&#39;`. Anything else is copied as
+/// is.
+pub(crate) fn unescape(text: &mut String, escaped: &str) {
+    let mut rest = escaped;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..].find(';').and_then(|end| {
+            let name = &rest[1..end + 1];
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => match name.strip_prefix('#') {
+                    Some(hex) if hex.starts_with('x') || hex.starts_with('X') => {
+                        u32::from_str_radix(&hex[1..], 16).ok().and_then(char::from_u32)
+                    },
+                    Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                    None => None,
+                },
+            };
+            c.map(|c| (c, end + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                text.push(c);
+                rest = &rest[len..];
+            },
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    text.push_str(rest);
+}
+
+/// Checks whether a token can go on the same line as the text around it.
+fn is_inline(token: Token<'_>) -> bool {
+    match token {
+        Token::Open { name, .. } | Token::Close { name, .. } | Token::Void { name, .. } => {
+            is_one_of(name, INLINE)
+        },
+        Token::Text(_) => true,
+        Token::Unterminated(_) => false,
+    }
+}
+
+/// If everything inside the element opened at `tokens[start]` is inline,
+/// returns the index of its closing tag.
+fn inline_close(tokens: &[Token<'_>], start: usize) -> Option<usize> {
+    let name = match tokens[start] {
+        Token::Open { name, .. } if !is_one_of(name, PREFORMATTED) => name,
+        _ => return None,
+    };
+    let mut depth = 0;
+    for (i, &token) in tokens.iter().enumerate().skip(start + 1) {
+        match token {
+            Token::Close { name: close, .. } if depth == 0 => {
+                return if close.eq_ignore_ascii_case(name) { Some(i) } else { None };
+            },
+            _ if !is_inline(token) => return None,
+            Token::Open { .. } => depth += 1,
+            Token::Close { .. } => depth -= 1,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Returns the source text of a token.
+fn token_str(token: Token<'_>) -> &str {
+    match token {
+        Token::Open { tag, .. } | Token::Close { tag, .. } | Token::Void { tag, .. } => tag,
+        Token::Text(text) | Token::Unterminated(text) => text,
+    }
+}
+
+impl<T: AsRef<str>> PreEscaped<T> {
+    /// Writes the markup with one block of content per line, indented by
+    /// `indent_step` spaces per level of nesting.
+    ///
+    /// Every line starts with at least `base_indent` spaces, so that the
+    /// result lines up when pasted into an indented document.
+    ///
+    /// Block elements like `div` and `p` start new lines, while text and
+    /// inline elements like `a` and `em` stay on the line they're in. A
+    /// block element with only inline content goes on one line. Whitespace
+    /// around the lines is replaced by the indentation, so this is best
+    /// kept to debugging and generated files. The contents of `pre`,
+    /// `textarea`, `script`, and `style` are copied as is.
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// use maud::html;
+    ///
+    /// let markup = html! { ul { li { "Cupcakes" } li { b { "Muffins" } } } };
+    /// let mut s = String::new();
+    /// markup.render_indented(&mut s, 4, 2).unwrap();
+    /// assert_eq!(s, concat!(
+    ///     "    <ul>\n",
+    ///     "      <li>Cupcakes</li>\n",
+    ///     "      <li><b>Muffins</b></li>\n",
+    ///     "    </ul>\n"));
+    /// ```
+    pub fn render_indented<W: fmt::Write + ?Sized>(
+        &self,
+        w: &mut W,
+        base_indent: usize,
+        indent_step: usize,
+    ) -> fmt::Result {
+        let html = self.0.as_ref();
+        let tokens = tokenize(html);
+        let mut depth = 0;
+        // Inline content waiting for the end of its line
+        let mut pending = String::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let indent = base_indent + depth * indent_step;
+            let token = tokens[i];
+            if is_inline(token) {
+                pending.push_str(token_str(token));
+                i += 1;
+                continue;
+            }
+            if !pending.trim().is_empty() {
+                line(w, indent, pending.trim())?;
+            }
+            pending.clear();
+            match token {
+                Token::Open { name, tag } if is_one_of(name, PREFORMATTED) => {
+                    // Copy everything up to the matching close tag
+                    let start = tag.as_ptr() as usize - html.as_ptr() as usize;
+                    let mut end = html.len();
+                    while i + 1 < tokens.len() {
+                        i += 1;
+                        if let Token::Close { name: close, tag } = tokens[i] {
+                            if close.eq_ignore_ascii_case(name) {
+                                end = tag.as_ptr() as usize - html.as_ptr() as usize + tag.len();
+                                break;
+                            }
+                        }
+                    }
+                    line(w, indent, &html[start..end])?;
+                },
+                Token::Open { tag, .. } => match inline_close(&tokens, i) {
+                    // Keep elements with only inline content on one line
+                    Some(close) => {
+                        let inner = tokens[i + 1..close].iter().map(|&token| token_str(token)).collect::<String>();
+                        line(w, indent, &format!("{}{}{}", tag, inner.trim(), token_str(tokens[close])))?;
+                        i = close;
+                    },
+                    None => {
+                        line(w, indent, tag)?;
+                        depth += 1;
+                    },
+                },
+                Token::Close { tag, .. } => {
+                    depth = depth.saturating_sub(1);
+                    line(w, base_indent + depth * indent_step, tag)?;
+                },
+                Token::Void { tag, .. } | Token::Unterminated(tag) => line(w, indent, tag)?,
+                Token::Text(_) => unreachable!("text is always inline"),
+            }
+            i += 1;
+        }
+        if !pending.trim().is_empty() {
+            line(w, base_indent + depth * indent_step, pending.trim())?;
+        }
+        Ok(())
+    }
+}
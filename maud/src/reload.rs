@@ -0,0 +1,595 @@
+//! Templates that are read and interpreted at runtime, for a faster
+//! edit-refresh loop during development.
+//!
+//! With the `hot-reload` feature enabled, [`html_include!`] loads its
+//! template through this module instead of compiling it in, so changes
+//! to the template show up on the next render without a rebuild. The
+//! interpreter understands a subset of Maud:
+//!
+//! - string literals, like `"Hello"`
+//! - elements, both with a body (`p { ... }`) and void (`br;`)
+//! - blocks, like `{ "a" "b" }`
+//! - attributes with a literal or spliced value (`href="/"`,
+//!   `href=(url)`), and empty attributes (`checked?`)
+//! - class and ID shorthand with a literal or spliced name (`.big`,
+//!   `#main`, `.(size)`)
+//! - splices of a single variable, like `(name)`
+//!
+//! Control structures (`@if`, `@for`, ...), toggles like `.big[cond]`,
+//! and splices of anything other than a plain variable aren't supported.
+//! [`html_include!`] compiles templates that use them as usual, even with
+//! the feature enabled.
+//!
+//! Splices are looked up by name in the variables passed to
+//! [`render_file`]. A template is read again whenever its modification
+//! time or size changes.
+//!
+//! [`html_include!`]: ../macro.html_include.html
+//! [`render_file`]: fn.render_file.html
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::rt::ClassList;
+use crate::{Escaper, Markup, PreEscaped, Render};
+
+/// An error from loading or rendering a template.
+#[derive(Clone, Debug)]
+pub struct Error {
+    path: PathBuf,
+    line: Option<usize>,
+    message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl error::Error for Error {}
+
+/// Renders the template at `path`, with splices looked up in `vars`.
+///
+/// If something goes wrong, the error is rendered in place of the
+/// template, so that it shows up in the browser.
+pub fn render_file<P: AsRef<Path>>(path: P, vars: &[(&str, &dyn Render)]) -> Markup {
+    try_render_file(path, vars).unwrap_or_else(|error| {
+        let mut buffer = String::from("<pre>");
+        let _ = write!(Escaper::new(&mut buffer), "{}", error);
+        buffer.push_str("</pre>");
+        PreEscaped(buffer)
+    })
+}
+
+/// Like [`render_file`](fn.render_file.html), but returns any error
+/// instead of rendering it.
+pub fn try_render_file<P: AsRef<Path>>(path: P, vars: &[(&str, &dyn Render)]) -> Result<Markup, Error> {
+    let path = path.as_ref();
+    let template = load(path)?;
+    let mut buffer = String::new();
+    for part in template.iter() {
+        match *part {
+            Part::Static(ref string) => buffer.push_str(string),
+            Part::Splice(ref name) => lookup(path, vars, name)?.render_to(&mut buffer),
+            Part::Classes(ref classes) => {
+                let mut list = ClassList::new();
+                for class in classes {
+                    match *class {
+                        Class::Static(ref name) => list.push(&PreEscaped(name.as_str())),
+                        Class::Splice(ref name) => list.push(lookup(path, vars, name)?),
+                    }
+                }
+                list.render_to(&mut buffer);
+            },
+        }
+    }
+    Ok(PreEscaped(buffer))
+}
+
+fn lookup<'a>(path: &Path, vars: &[(&str, &'a dyn Render)], name: &str) -> Result<&'a dyn Render, Error> {
+    vars.iter()
+        .find(|&&(var, _)| var == name)
+        .map(|&(_, value)| value)
+        .ok_or_else(|| Error {
+            path: path.to_path_buf(),
+            line: None,
+            message: format!("unknown variable `{}`; rebuild to pass it to the template", name),
+        })
+}
+
+////////////////////////////////////////////////////////
+
+/// A piece of a template, with the static parts already escaped.
+#[derive(Debug)]
+enum Part {
+    Static(String),
+    Splice(String),
+    Classes(Vec<Class>),
+}
+
+#[derive(Debug)]
+enum Class {
+    Static(String),
+    Splice(String),
+}
+
+struct Cached {
+    modified: Option<SystemTime>,
+    len: u64,
+    template: Result<Arc<Vec<Part>>, Error>,
+}
+
+static CACHE: Mutex<Option<HashMap<PathBuf, Cached>>> = Mutex::new(None);
+
+/// Returns the parsed template, reading it again if it has changed.
+fn load(path: &Path) -> Result<Arc<Vec<Part>>, Error> {
+    let error = |message: String| Error { path: path.to_path_buf(), line: None, message };
+    let metadata = fs::metadata(path).map_err(|e| error(e.to_string()))?;
+    let modified = metadata.modified().ok();
+    let len = metadata.len();
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some(cached) = cache.get(path) {
+        if cached.modified == modified && cached.len == len && modified.is_some() {
+            return cached.template.clone();
+        }
+    }
+    let source = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let template = parse(&source)
+        .map(Arc::new)
+        .map_err(|(line, message)| Error { path: path.to_path_buf(), line: Some(line), message });
+    cache.insert(path.to_path_buf(), Cached { modified, len, template: template.clone() });
+    template
+}
+
+////////////////////////////////////////////////////////
+
+type ParseResult<T> = Result<T, (usize, String)>;
+
+#[derive(Clone, Debug)]
+enum TokenKind {
+    Ident(String),
+    Str(String),
+    Punct(char),
+    Group(char, Vec<Token>),
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+}
+
+/// Splits the source into tokens, in roughly the same way as the Rust
+/// lexer.
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn tokens(&mut self, close: Option<char>) -> ParseResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            let line = self.line;
+            let c = match self.next_char() {
+                Some(c) => c,
+                None => match close {
+                    Some(close) => return Err((line, format!("unclosed delimiter, expected `{}`", close))),
+                    None => return Ok(tokens),
+                },
+            };
+            let kind = match c {
+                '(' => TokenKind::Group('(', self.tokens(Some(')'))?),
+                '[' => TokenKind::Group('[', self.tokens(Some(']'))?),
+                '{' => TokenKind::Group('{', self.tokens(Some('}'))?),
+                ')' | ']' | '}' if Some(c) == close => return Ok(tokens),
+                ')' | ']' | '}' => return Err((line, format!("unexpected `{}`", c))),
+                '"' => TokenKind::Str(self.string()?),
+                'r' if matches!(self.chars.peek(), Some('"') | Some('#')) => {
+                    TokenKind::Str(self.raw_string()?)
+                },
+                c if c.is_alphabetic() || c == '_' => {
+                    let mut ident = c.to_string();
+                    while let Some(&c) = self.chars.peek() {
+                        if !c.is_alphanumeric() && c != '_' {
+                            break;
+                        }
+                        ident.push(c);
+                        self.next_char();
+                    }
+                    TokenKind::Ident(ident)
+                },
+                c if c.is_ascii_digit() => {
+                    return Err((line, "numbers aren't supported; use a string instead".to_string()));
+                },
+                c => TokenKind::Punct(c),
+            };
+            tokens.push(Token { kind, line });
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> ParseResult<()> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next_char();
+                },
+                Some('/') => {
+                    let mut rest = self.chars.clone();
+                    rest.next();
+                    match rest.next() {
+                        Some('/') => {
+                            while !matches!(self.next_char(), Some('\n') | None) {}
+                        },
+                        Some('*') => {
+                            let line = self.line;
+                            self.next_char();
+                            self.next_char();
+                            let mut depth = 1;
+                            while depth > 0 {
+                                match self.next_char() {
+                                    Some('*') if self.chars.peek() == Some(&'/') => {
+                                        self.next_char();
+                                        depth -= 1;
+                                    },
+                                    Some('/') if self.chars.peek() == Some(&'*') => {
+                                        self.next_char();
+                                        depth += 1;
+                                    },
+                                    Some(_) => {},
+                                    None => return Err((line, "unterminated block comment".to_string())),
+                                }
+                            }
+                        },
+                        _ => return Ok(()),
+                    }
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn string(&mut self) -> ParseResult<String> {
+        let line = self.line;
+        let mut string = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.next_char() {
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('0') => string.push('\0'),
+                    Some('\\') => string.push('\\'),
+                    Some('\'') => string.push('\''),
+                    Some('"') => string.push('"'),
+                    Some('\n') => {
+                        while matches!(self.chars.peek(), Some(c) if c.is_whitespace()) {
+                            self.next_char();
+                        }
+                    },
+                    Some('x') => {
+                        let hex = (self.next_char(), self.next_char());
+                        let c = match hex {
+                            (Some(a), Some(b)) => u8::from_str_radix(&format!("{}{}", a, b), 16).ok()
+                                .filter(|&c| c < 0x80),
+                            _ => None,
+                        };
+                        string.push(c.ok_or((self.line, "invalid `\\x` escape".to_string()))? as char);
+                    },
+                    Some('u') => {
+                        let mut hex = String::new();
+                        if self.next_char() == Some('{') {
+                            while let Some(c) = self.next_char() {
+                                if c == '}' {
+                                    break;
+                                }
+                                hex.push(c);
+                            }
+                        }
+                        let c = u32::from_str_radix(&hex.replace('_', ""), 16).ok().and_then(std::char::from_u32);
+                        string.push(c.ok_or((self.line, "invalid `\\u` escape".to_string()))?);
+                    },
+                    _ => return Err((self.line, "unknown escape in string".to_string())),
+                },
+                Some(c) => string.push(c),
+                None => return Err((line, "unterminated string".to_string())),
+            }
+        }
+    }
+
+    fn raw_string(&mut self) -> ParseResult<String> {
+        let line = self.line;
+        let mut hashes = 0;
+        while self.chars.peek() == Some(&'#') {
+            self.next_char();
+            hashes += 1;
+        }
+        if self.next_char() != Some('"') {
+            return Err((line, "expected `\"` in raw string".to_string()));
+        }
+        let end = format!("\"{}", "#".repeat(hashes));
+        let mut string = String::new();
+        loop {
+            match self.next_char() {
+                Some(c) => string.push(c),
+                None => return Err((line, "unterminated raw string".to_string())),
+            }
+            if string.ends_with(&end) {
+                string.truncate(string.len() - end.len());
+                return Ok(string);
+            }
+        }
+    }
+}
+
+fn parse(source: &str) -> ParseResult<Vec<Part>> {
+    let tokens = Lexer { chars: source.chars().peekable(), line: 1 }.tokens(None)?;
+    let mut builder = Builder::default();
+    Parser { tokens: &tokens, pos: 0 }.markups(&mut builder)?;
+    Ok(builder.finish())
+}
+
+/// Collects parts, joining adjacent static strings.
+#[derive(Default)]
+struct Builder {
+    parts: Vec<Part>,
+    tail: String,
+}
+
+impl Builder {
+    fn push_str(&mut self, string: &str) {
+        self.tail.push_str(string);
+    }
+
+    fn push_escaped(&mut self, string: &str) {
+        let _ = Escaper::new(&mut self.tail).write_str(string);
+    }
+
+    fn push_part(&mut self, part: Part) {
+        self.cut();
+        self.parts.push(part);
+    }
+
+    fn cut(&mut self) {
+        if !self.tail.is_empty() {
+            self.parts.push(Part::Static(std::mem::take(&mut self.tail)));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Part> {
+        self.cut();
+        self.parts
+    }
+}
+
+enum Value {
+    Static(String),
+    Splice(String),
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    /// The line to blame for an error at the current position.
+    fn line(&self) -> usize {
+        self.peek()
+            .or_else(|| self.tokens.last())
+            .map_or(1, |token| token.line)
+    }
+
+    fn markups(&mut self, build: &mut Builder) -> ParseResult<()> {
+        while let Some(token) = self.next() {
+            match token.kind {
+                TokenKind::Str(ref string) => build.push_escaped(string),
+                TokenKind::Group('(', ref inner) => {
+                    let name = splice_name(token.line, inner)?;
+                    build.push_part(Part::Splice(name));
+                },
+                TokenKind::Group('{', ref inner) => Parser { tokens: inner, pos: 0 }.markups(build)?,
+                TokenKind::Ident(_) => {
+                    self.pos -= 1;
+                    self.element(build)?;
+                },
+                TokenKind::Punct('@') => {
+                    return Err((token.line, "`@` keywords aren't supported when reloading templates".to_string()));
+                },
+                _ => return Err((token.line, "expected a string, element, or splice".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    fn element(&mut self, build: &mut Builder) -> ParseResult<()> {
+        let name = self.name().expect("element name");
+        build.push_str("<");
+        build.push_escaped(&name);
+        let mut classes = Vec::new();
+        let mut id = None;
+        let mut attrs = Builder::default();
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                Some(TokenKind::Punct('.')) => {
+                    self.pos += 1;
+                    classes.push(match self.class_or_id_name()? {
+                        Value::Static(name) => {
+                            let mut escaped = String::new();
+                            let _ = Escaper::new(&mut escaped).write_str(&name);
+                            Class::Static(escaped)
+                        },
+                        Value::Splice(name) => Class::Splice(name),
+                    });
+                },
+                Some(TokenKind::Punct('#')) => {
+                    self.pos += 1;
+                    id = Some(self.class_or_id_name()?);
+                },
+                Some(TokenKind::Ident(_)) => self.attribute(&mut attrs)?,
+                // A quoted attribute name, like `"@click"="..."`
+                Some(TokenKind::Str(_)) if matches!(
+                    self.tokens.get(self.pos + 1).map(|token| &token.kind),
+                    Some(TokenKind::Punct('=')) | Some(TokenKind::Punct('?'))
+                ) => self.attribute(&mut attrs)?,
+                _ => break,
+            }
+            if let Some(TokenKind::Group('[', _)) = self.peek().map(|token| &token.kind) {
+                return Err((self.line(), "toggles aren't supported when reloading templates".to_string()));
+            }
+        }
+        // Classes and IDs come first, as in compiled templates
+        if !classes.is_empty() {
+            build.push_str(" class=\"");
+            build.push_part(Part::Classes(classes));
+            build.push_str("\"");
+        }
+        if let Some(id) = id {
+            build.push_str(" id=\"");
+            push_value(build, id);
+            build.push_str("\"");
+        }
+        for part in attrs.finish() {
+            match part {
+                Part::Static(string) => build.push_str(&string),
+                part => build.push_part(part),
+            }
+        }
+        match self.next() {
+            Some(Token { kind: TokenKind::Punct(';'), .. }) => build.push_str(">"),
+            Some(Token { kind: TokenKind::Group('{', ref inner), .. }) => {
+                build.push_str(">");
+                Parser { tokens: inner, pos: 0 }.markups(build)?;
+                build.push_str("</");
+                build.push_escaped(&name);
+                build.push_str(">");
+            },
+            _ => {
+                let line = self.tokens.get(self.pos - 1).map_or(self.line(), |token| token.line);
+                return Err((line, format!("expected a body or `;` after element `{}`", name)));
+            },
+        }
+        Ok(())
+    }
+
+    fn attribute(&mut self, build: &mut Builder) -> ParseResult<()> {
+        let mut name = match self.peek() {
+            Some(Token { kind: TokenKind::Str(ref name), .. }) => {
+                self.pos += 1;
+                name.clone()
+            },
+            _ => self.name().expect("attribute name"),
+        };
+        if let Some(TokenKind::Punct(':')) = self.peek().map(|token| &token.kind) {
+            self.pos += 1;
+            match self.name() {
+                Some(local) => {
+                    name.push(':');
+                    name.push_str(&local);
+                },
+                None => return Err((self.line(), "expected a name after `:`".to_string())),
+            }
+        }
+        build.push_str(" ");
+        build.push_escaped(&name);
+        match self.next().map(|token| &token.kind) {
+            Some(TokenKind::Punct('=')) => {
+                let value = match self.next() {
+                    Some(Token { kind: TokenKind::Str(ref string), .. }) => Value::Static(string.clone()),
+                    Some(Token { kind: TokenKind::Group('(', ref inner), line }) => {
+                        Value::Splice(splice_name(*line, inner)?)
+                    },
+                    _ => return Err((self.line(), format!("expected a string or splice after `{}=`", name))),
+                };
+                build.push_str("=\"");
+                push_value(build, value);
+                build.push_str("\"");
+            },
+            Some(TokenKind::Punct('?')) => {},
+            _ => return Err((self.line(), format!("expected `=` or `?` after attribute `{}`", name))),
+        }
+        Ok(())
+    }
+
+    fn class_or_id_name(&mut self) -> ParseResult<Value> {
+        if let Some(name) = self.name() {
+            return Ok(Value::Static(name));
+        }
+        match self.next() {
+            Some(Token { kind: TokenKind::Str(ref string), .. }) => Ok(Value::Static(string.clone())),
+            Some(Token { kind: TokenKind::Group('(', ref inner), line }) => {
+                Ok(Value::Splice(splice_name(*line, inner)?))
+            },
+            _ => Err((self.line(), "expected a class or ID name".to_string())),
+        }
+    }
+
+    /// Parses a name like `data-foo`.
+    fn name(&mut self) -> Option<String> {
+        let mut name = match self.peek() {
+            Some(Token { kind: TokenKind::Ident(ref ident), .. }) => ident.clone(),
+            _ => return None,
+        };
+        self.pos += 1;
+        let mut expect_ident = false;
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                Some(TokenKind::Punct('-')) => {
+                    name.push('-');
+                    expect_ident = true;
+                },
+                Some(TokenKind::Ident(ref ident)) if expect_ident => {
+                    name.push_str(ident);
+                    expect_ident = false;
+                },
+                _ => return Some(name),
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+fn push_value(build: &mut Builder, value: Value) {
+    match value {
+        Value::Static(string) => build.push_escaped(&string),
+        Value::Splice(name) => build.push_part(Part::Splice(name)),
+    }
+}
+
+fn splice_name(line: usize, inner: &[Token]) -> ParseResult<String> {
+    match inner {
+        [Token { kind: TokenKind::Ident(ref name), .. }] => Ok(name.clone()),
+        _ => Err((line, "only splices of a single variable, like `(name)`, are supported when reloading templates".to_string())),
+    }
+}
@@ -0,0 +1,81 @@
+//! Tracing of spliced values, for finding out what a template actually
+//! rendered.
+//!
+//! With the `trace` feature enabled, every splice reports its rendered
+//! text and source location while tracing is on. Tracing starts off,
+//! unless the `MAUD_TRACE` environment variable is set to `1`; turn it
+//! on and off with [`set_trace`]. By default each splice is printed to
+//! standard error, or [`set_callback`] can collect them instead.
+//!
+//! With the feature off, templates compile to the same code as before.
+//!
+//! [`set_trace`]: fn.set_trace.html
+//! [`set_callback`]: fn.set_callback.html
+
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::RwLock;
+
+/// A splice that was rendered while tracing.
+#[derive(Clone, Copy, Debug)]
+pub struct Splice<'a> {
+    /// The file containing the splice.
+    pub file: &'static str,
+    /// The line of the splice, starting from 1.
+    pub line: u32,
+    /// The column of the splice, starting from 1.
+    pub column: u32,
+    /// The rendered, escaped text of the spliced value.
+    pub value: &'a str,
+}
+
+type Callback = Box<dyn Fn(&Splice) + Send + Sync>;
+
+const UNSET: u8 = 0;
+const OFF: u8 = 1;
+const ON: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(UNSET);
+static CALLBACK: RwLock<Option<Callback>> = RwLock::new(None);
+
+/// Turns tracing on or off.
+pub fn set_trace(enabled: bool) {
+    STATE.store(if enabled { ON } else { OFF }, Ordering::Relaxed);
+}
+
+/// Sends each traced splice to `callback`, instead of printing it.
+pub fn set_callback<F: Fn(&Splice) + Send + Sync + 'static>(callback: F) {
+    *CALLBACK.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(callback));
+}
+
+/// Goes back to printing traced splices to standard error.
+pub fn clear_callback() {
+    *CALLBACK.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+#[doc(hidden)]
+pub fn enabled() -> bool {
+    match STATE.load(Ordering::Relaxed) {
+        UNSET => {
+            let enabled = env::var_os("MAUD_TRACE").is_some_and(|value| value == "1");
+            // Don't overwrite a call to `set_trace` that raced with us
+            let _ = STATE.compare_exchange(
+                UNSET,
+                if enabled { ON } else { OFF },
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+            STATE.load(Ordering::Relaxed) == ON
+        },
+        state => state == ON,
+    }
+}
+
+#[doc(hidden)]
+pub fn splice(file: &'static str, line: u32, column: u32, value: &str) {
+    let splice = Splice { file, line, column, value };
+    match *CALLBACK.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref callback) => callback(&splice),
+        None => eprintln!("{}:{}:{}: {}", file, line, column, value),
+    }
+}
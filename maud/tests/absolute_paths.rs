@@ -1,5 +1,3 @@
-#![feature(proc_macro_hygiene)]
-
 // Make sure `std` is available but the prelude isn't
 #![no_std]
 extern crate std;
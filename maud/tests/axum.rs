@@ -0,0 +1,26 @@
+#![cfg(feature = "axum")]
+
+use axum_core::response::IntoResponse;
+use maud::{html, Markup};
+
+async fn handler(name: String) -> Markup {
+    html! {
+        h1 { "Hello, " (name) "!" }
+    }
+}
+
+/// Checks that `handler` has the shape of an axum handler.
+fn assert_handler<F, Fut>(_: F)
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future,
+    Fut::Output: IntoResponse,
+{}
+
+#[test]
+fn into_response() {
+    assert_handler(handler);
+    let response = html!(p { "Hi!" }).into_response();
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.headers()[http::header::CONTENT_TYPE], "text/html; charset=utf-8");
+}
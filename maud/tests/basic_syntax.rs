@@ -1,5 +1,3 @@
-#![feature(proc_macro_hygiene)]
-
 use maud::{Markup, html};
 
 #[test]
@@ -56,6 +54,12 @@ fn empty_elements_slash() {
     assert_eq!(s, "pinkie<br>pie");
 }
 
+#[test]
+fn empty_custom_elements() {
+    let s = html!(my-icon; my-icon name="star"; "pie").into_string();
+    assert_eq!(s, r#"<my-icon><my-icon name="star">pie"#);
+}
+
 #[test]
 fn simple_attributes() {
     let s = html! {
@@ -98,6 +102,33 @@ fn toggle_empty_attributes_braces() {
     assert_eq!(s, r#"<input checked>"#);
 }
 
+#[test]
+fn toggle_empty_attributes_shorthand() {
+    let is_selected = true;
+    let s = html!({
+        option selected[is_selected] { "Yes" }
+        option selected[!is_selected] { "No" }
+    }).into_string();
+    assert_eq!(s, concat!(
+            r#"<option selected>Yes</option>"#,
+            r#"<option>No</option>"#));
+}
+
+#[test]
+fn quoted_attribute_names() {
+    let s = html! {
+        div x-data="{ open: false }" {
+            button "@click"="open = !open" ":disabled"?[false] { "Toggle" }
+            input "wire:model.lazy"="name" "x-on:keydown.enter"?;
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<div x-data="{ open: false }">"#,
+            r#"<button @click="open = !open">Toggle</button>"#,
+            r#"<input wire:model.lazy="name" x-on:keydown.enter>"#,
+            r#"</div>"#));
+}
+
 #[test]
 fn colons_in_names() {
     let s = html!(pon-pon:controls-alpha { a on:click="yay()" { "Yay!" } }).into_string();
@@ -155,7 +186,7 @@ fn toggle_classes() {
         html!(p.cupcake[is_cupcake].muffin[is_muffin] { "Testing!" })
     }
     assert_eq!(test(true, true).into_string(), r#"<p class="cupcake muffin">Testing!</p>"#);
-    assert_eq!(test(false, true).into_string(), r#"<p class=" muffin">Testing!</p>"#);
+    assert_eq!(test(false, true).into_string(), r#"<p class="muffin">Testing!</p>"#);
     assert_eq!(test(true, false).into_string(), r#"<p class="cupcake">Testing!</p>"#);
     assert_eq!(test(false, false).into_string(), r#"<p class="">Testing!</p>"#);
 }
@@ -180,10 +211,64 @@ fn mixed_classes() {
     fn test(is_muffin: bool) -> Markup {
         html!(p.cupcake.muffin[is_muffin].lamington { "Testing!" })
     }
-    assert_eq!(test(true).into_string(), r#"<p class="cupcake lamington muffin">Testing!</p>"#);
+    assert_eq!(test(true).into_string(), r#"<p class="cupcake muffin lamington">Testing!</p>"#);
     assert_eq!(test(false).into_string(), r#"<p class="cupcake lamington">Testing!</p>"#);
 }
 
+#[test]
+fn class_flags() {
+    fn test(flags: &[(&str, bool)]) -> Markup {
+        html!(p class=toggle[flags] { "Testing!" })
+    }
+    assert_eq!(
+        test(&[("cupcake", false), ("muffin", true), ("lamington", false), ("scone", true)]).into_string(),
+        r#"<p class="muffin scone">Testing!</p>"#,
+    );
+    assert_eq!(test(&[("cupcake", true)]).into_string(), r#"<p class="cupcake">Testing!</p>"#);
+    assert_eq!(test(&[("cupcake", false), ("muffin", false)]).into_string(), "<p>Testing!</p>");
+    assert_eq!(test(&[]).into_string(), "<p>Testing!</p>");
+}
+
+#[test]
+fn class_flags_map() {
+    let mut flags = std::collections::BTreeMap::new();
+    flags.insert("cupcake".to_string(), true);
+    flags.insert("<muffin>".to_string(), true);
+    flags.insert("scone".to_string(), false);
+    let s = html!(p#treats class=toggle[&flags] title="Treats" { "Testing!" }).into_string();
+    assert_eq!(s, r#"<p id="treats" class="&lt;muffin&gt; cupcake" title="Treats">Testing!</p>"#);
+}
+
+#[test]
+fn default_attributes() {
+    let base = [("class", "btn"), ("type", "button"), ("title", "Click \"me\"")];
+    let s = html! {
+        button @defaults(base.iter().cloned()) { "Default" }
+        button.btn-danger @defaults(base.iter().cloned()) type="submit" { "Override" }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<button class="btn" type="button" title="Click &quot;me&quot;">Default</button>"#,
+            r#"<button class="btn-danger" type="submit" title="Click &quot;me&quot;">Override</button>"#));
+}
+
+#[test]
+fn default_attributes_cfg() {
+    let base = [("type", "button"), ("title", "Base")];
+    // `any()` is never true, and `all()` always is
+    let s = html! {
+        button @defaults(base.iter().cloned()) @cfg(any()) type="submit" @cfg(all()) title="Own" {}
+    }.into_string();
+    assert_eq!(s, r#"<button title="Own" type="button"></button>"#);
+}
+
+#[test]
+fn default_attributes_last_wins() {
+    let base = vec![("class", "card".to_string()), ("role", "region".to_string())];
+    let overrides = vec![("class", "card wide".to_string()), ("<bad>", "dropped".to_string())];
+    let s = html!(div @defaults(base.into_iter().chain(overrides)) {}).into_string();
+    assert_eq!(s, r#"<div role="region" class="card wide"></div>"#);
+}
+
 #[test]
 fn id_shorthand() {
     let s = html!(p { "Hi, " span#thing { "Lyra" } "!" }).into_string();
@@ -225,3 +310,108 @@ fn div_shorthand_id_with_attrs() {
     let s = html!(#unique-id contenteditable? dir="rtl" .awesome-class {}).into_string();
     assert_eq!(s, r#"<div class="awesome-class" id="unique-id" contenteditable dir="rtl"></div>"#);
 }
+
+#[test]
+fn trailing_text_after_child() {
+    let s = html! {
+        div { span { "a" } "b" }
+        p { "x" br; "y" }
+    }.into_string();
+    assert_eq!(s, "<div><span>a</span>b</div><p>x<br>y</p>");
+}
+
+#[test]
+fn adjacent_literals() {
+    let s = html! {
+        p { "line one " "line two" }
+        p {
+            "a long paragraph that goes on \
+             and on"
+        }
+    }.into_string();
+    assert_eq!(s, "<p>line one line two</p><p>a long paragraph that goes on and on</p>");
+}
+
+#[test]
+fn template_element() {
+    // `template` content is inert in the browser, but it's still markup:
+    // children are serialized as usual, and only text is escaped
+    let s = html! {
+        template#row {
+            tr.row { td { "<name>" } td { (maud::PreEscaped("<b>raw</b>")) } }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+        r#"<template id="row"><tr class="row"><td>&lt;name&gt;</td>"#,
+        "<td><b>raw</b></td></tr></template>",
+    ));
+}
+
+#[test]
+fn processing_instructions() {
+    let s = html!(xhtml = true, {
+        @pi r#"xml version="1.0" encoding="UTF-8""#
+        @pi "xml-stylesheet href=\"feed.xsl\" type=\"text/xsl\""
+        feed xmlns="http://www.w3.org/2005/Atom" {
+            title { "Ponies & friends" }
+            link href="/feed" /
+        }
+    }).into_string();
+    assert_eq!(s, concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<?xml-stylesheet href="feed.xsl" type="text/xsl"?>"#,
+            r#"<feed xmlns="http://www.w3.org/2005/Atom">"#,
+            r#"<title>Ponies &amp; friends</title>"#,
+            r#"<link href="/feed" />"#,
+            r#"</feed>"#));
+}
+
+#[test]
+fn ie_conditional_comments() {
+    let version = 9;
+    let s = html! {
+        head {
+            @ie_comment "lt IE 9" {
+                link rel="stylesheet" href="/ie.css";
+            }
+            @ie_comment "IE" { script src={ "/shim-" (version) ".js" } {} }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            "<head>",
+            r#"<!--[if lt IE 9]><link rel="stylesheet" href="/ie.css"><![endif]-->"#,
+            r#"<!--[if IE]><script src="/shim-9.js"></script><![endif]-->"#,
+            "</head>"));
+}
+
+#[test]
+fn spaceless() {
+    let name = "Rarity";
+    let s = html! {
+        @spaceless {
+            "\n"
+            ul { "  " li { "a" } "\n  " li { "b" } "\n" }
+            p { "Hello, " b { (name) } " and " i { "friends" } }
+            " " (name) " "
+            @for x in 1..3 { " " span { (x) } " " }
+        }
+        " " b { "Outside" }
+    }.into_string();
+    assert_eq!(s, concat!(
+            "<ul><li>a</li><li>b</li></ul>",
+            "<p>Hello, <b>Rarity</b> and <i>friends</i></p>",
+            " Rarity ",
+            " <span>1</span>  <span>2</span> ",
+            " <b>Outside</b>"));
+}
+
+#[test]
+fn spaceless_keeps_preformatted() {
+    let s = html! {
+        @spaceless {
+            div { " " pre { b { "a" } " " i { "b" } "\n" } " " }
+            textarea { " " }
+        }
+    }.into_string();
+    assert_eq!(s, "<div><pre><b>a</b> <i>b</i>\n</pre></div><textarea> </textarea>");
+}
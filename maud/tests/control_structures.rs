@@ -1,5 +1,3 @@
-#![feature(proc_macro_hygiene)]
-
 use maud::html;
 
 #[test]
@@ -52,7 +50,7 @@ fn if_let() {
 
 #[test]
 fn while_expr() {
-    let mut numbers = (0..3).into_iter().peekable();
+    let mut numbers = (0..3).peekable();
     let s = html! {
         ul {
             @while numbers.peek().is_some() {
@@ -64,8 +62,9 @@ fn while_expr() {
 }
 
 #[test]
+#[allow(clippy::while_let_on_iterator)]
 fn while_let_expr() {
-    let mut numbers = (0..3).into_iter();
+    let mut numbers = 0..3;
     let s = html! {
         ul {
             @while let Some(n) = numbers.next() {
@@ -94,6 +93,31 @@ fn for_expr() {
             "</ul>"));
 }
 
+#[test]
+fn nested_for_grid() {
+    let cells = [1, 2, 3, 4, 5, 6];
+    let s = html! {
+        div.grid {
+            @for row in cells.chunks(3) {
+                div.row {
+                    @for cell in row {
+                        div.cell { (cell) }
+                    }
+                }
+            }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<div class="grid">"#,
+            r#"<div class="row">"#,
+            r#"<div class="cell">1</div><div class="cell">2</div><div class="cell">3</div>"#,
+            "</div>",
+            r#"<div class="row">"#,
+            r#"<div class="cell">4</div><div class="cell">5</div><div class="cell">6</div>"#,
+            "</div>",
+            "</div>"));
+}
+
 #[test]
 fn match_expr() {
     for &(input, output) in &[(Some("yay"), "<div>yay</div>"), (None, "oh noes")] {
@@ -138,6 +162,7 @@ fn match_no_trailing_comma() {
 }
 
 #[test]
+#[allow(clippy::redundant_guards)]
 fn match_expr_with_guards() {
     for &(input, output) in &[(Some(1), "one"), (None, "none"), (Some(2), "2")] {
         let s = html! {
@@ -151,6 +176,20 @@ fn match_expr_with_guards() {
     }
 }
 
+#[test]
+fn match_str_literals() {
+    let render = |status: &str| html! {
+        @match status {
+            "ok" => { p.ok { "All good" } },
+            "err" => { p.err { "Something broke" } },
+            _ => { p { "Unknown status: " (status) } },
+        }
+    }.into_string();
+    assert_eq!(render("ok"), r#"<p class="ok">All good</p>"#);
+    assert_eq!(render("err"), r#"<p class="err">Something broke</p>"#);
+    assert_eq!(render("<?>"), "<p>Unknown status: &lt;?&gt;</p>");
+}
+
 #[test]
 fn match_in_attribute() {
     for &(input, output) in &[(1, "<span class=\"one\">1</span>"), (2, "<span class=\"two\">2</span>"), (3, "<span class=\"many\">3</span>")] {
@@ -165,6 +204,40 @@ fn match_in_attribute() {
     }
 }
 
+#[test]
+fn match_enum_in_attribute() {
+    enum Level { High, Low(&'static str) }
+    let render = |level| html! {
+        span class=@match level {
+            Level::High => "danger",
+            Level::Low(reason) => { "ok " (reason) },
+        } { "!" }
+    }.into_string();
+    assert_eq!(render(Level::High), r#"<span class="danger">!</span>"#);
+    assert_eq!(render(Level::Low("<fine>")), r#"<span class="ok &lt;fine&gt;">!</span>"#);
+}
+
+#[test]
+fn match_destructuring() {
+    struct Point { x: i32, y: i32, z: i32 }
+    struct Pair(&'static str, u32);
+    let point = Point { x: 1, y: 2, z: 3 };
+    let pair = Pair("cupcakes", 12);
+    let s = html! {
+        @match point {
+            Point { x: 0, .. } => "on the plane",
+            Point { x, y, .. } => { (x) "," (y) },
+        }
+        " "
+        @match pair {
+            Pair(name, count) if count > 10 => { b { (count) } " " (name) }
+            Pair(name, _) => (name),
+        }
+    }.into_string();
+    assert_eq!(point.z, 3);
+    assert_eq!(s, "1,2 <b>12</b> cupcakes");
+}
+
 #[test]
 fn let_expr() {
     let s = html! {
@@ -197,3 +270,364 @@ fn let_type_ascription() {
     }.into_string();
     assert_eq!(s, "I have 42 cupcakes!");
 }
+
+#[test]
+fn let_mut_updated_in_loop() {
+    let ponies = [("Rarity", 3), ("Applejack", 5)];
+    let s = html! {
+        @let mut total = 0;
+        ul {
+            @for &(ref name, cupcakes) in &ponies {
+                @do total += cupcakes;
+                li { (name) ": " (cupcakes) }
+            }
+        }
+        p { "Total: " (total) }
+    }.into_string();
+    assert_eq!(s, "<ul><li>Rarity: 3</li><li>Applejack: 5</li></ul><p>Total: 8</p>");
+}
+
+#[test]
+fn let_mut_collected_in_loop() {
+    let rows = [(7, "Rarity"), (3, "Applejack"), (12, "Fluttershy")];
+    let s = html! {
+        @let mut ids = Vec::new();
+        table {
+            @for &(id, name) in &rows {
+                @do ids.push(id);
+                tr#{ "row-" (id) } { td { (name) } }
+            }
+        }
+        p { "Rendered " (ids.len()) " rows: " (format!("{:?}", ids)) }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<table><tr id="row-7"><td>Rarity</td></tr><tr id="row-3"><td>Applejack</td></tr>"#,
+            r#"<tr id="row-12"><td>Fluttershy</td></tr></table>"#,
+            "<p>Rendered 3 rows: [7, 3, 12]</p>"));
+}
+
+#[test]
+fn let_ref_pattern() {
+    let pony = (String::from("Fluttershy"), 7);
+    let s = html! {
+        @let (ref name, ref mut count) = pony.clone();
+        @do *count += 1;
+        (name) " " (count)
+    }.into_string();
+    assert_eq!(s, "Fluttershy 8");
+}
+
+#[test]
+fn break_expr() {
+    let ponies = ["Apple Bloom", "Scootaloo", "Sweetie Belle", "Babs Seed"];
+    let s = html! {
+        ul {
+            @for (i, pony) in ponies.iter().enumerate() {
+                @if i == 3 {
+                    @break
+                }
+                li { (pony) }
+            }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            "<ul>",
+            "<li>Apple Bloom</li>",
+            "<li>Scootaloo</li>",
+            "<li>Sweetie Belle</li>",
+            "</ul>"));
+}
+
+#[test]
+fn continue_expr() {
+    let s = html! {
+        @for n in 1..7 {
+            @if n % 2 == 0 {
+                @continue;
+            }
+            (n)
+        }
+    }.into_string();
+    assert_eq!(s, "135");
+}
+
+#[test]
+fn break_after_limit() {
+    let ponies = ["Apple Bloom", "Scootaloo", "Sweetie Belle", "Babs Seed"];
+    let mut rest = vec!["Babs Seed", "Sweetie Belle"];
+    let mut s = String::new();
+    html!(&mut s, {
+        @for (i, pony) in ponies.iter().enumerate() {
+            b { (pony) }
+            @if i == 1 { @break }
+        } @sep {
+            ", "
+        }
+        " / "
+        @while let Some(pony) = rest.pop() {
+            @if pony.starts_with('B') { @break }
+            i { (pony) }
+        }
+    }).unwrap();
+    assert_eq!(s, "<b>Apple Bloom</b>, <b>Scootaloo</b> / <i>Sweetie Belle</i>");
+}
+
+#[test]
+fn cfg_expr() {
+    let s = html! {
+        @cfg(feature = "iron") {
+            "with iron"
+        }
+        @cfg(not(feature = "iron")) {
+            "without iron"
+        }
+        @cfg(any(test, feature = "iron")) {
+            ", testing"
+        }
+    }.into_string();
+    if cfg!(feature = "iron") {
+        assert_eq!(s, "with iron, testing");
+    } else {
+        assert_eq!(s, "without iron, testing");
+    }
+}
+
+#[test]
+fn cfg_expr_nested_in_writer() {
+    let mut s = String::new();
+    html!(&mut s, {
+        ul {
+            @for n in 1..3 {
+                li {
+                    (n)
+                    @cfg(test) { span.beta { "test" } }
+                    @cfg(not(test)) { span { "plain" } }
+                }
+            }
+        }
+    }).unwrap();
+    assert_eq!(s, r#"<ul><li>1<span class="beta">test</span></li><li>2<span class="beta">test</span></li></ul>"#);
+}
+
+#[test]
+fn cfg_expr_with_let() {
+    let s = html! {
+        @cfg(test) {
+            @let pony = "Trixie";
+            (pony)
+        }
+    }.into_string();
+    assert_eq!(s, "Trixie");
+}
+
+#[test]
+fn cfg_attr() {
+    let s = html! {
+        div
+            @cfg(debug_assertions) data-build="debug"
+            @cfg(not(debug_assertions)) data-build="release"
+            @cfg(test) hidden?
+            title="Trixie" {}
+    }.into_string();
+    let build = if cfg!(debug_assertions) { "debug" } else { "release" };
+    assert_eq!(s, format!(r#"<div data-build="{}" hidden title="Trixie"></div>"#, build));
+}
+
+#[test]
+fn for_sep() {
+    let render = |tags: &[&str]| html! {
+        @for tag in tags {
+            (tag)
+        } @sep {
+            ", "
+        }
+    }.into_string();
+    assert_eq!(render(&[]), "");
+    assert_eq!(render(&["pony"]), "pony");
+    assert_eq!(render(&["pony", "dragon", "<griffon>"]), "pony, dragon, &lt;griffon&gt;");
+}
+
+#[test]
+fn for_sep_with_element() {
+    let s = html! {
+        nav {
+            @for (i, crumb) in ["Home", "Ponies", "Rarity"].iter().enumerate() {
+                @let depth = i + 1;
+                a href={ "/" (depth) } { (crumb) }
+            } @sep {
+                span.sep { "·" }
+            }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<nav><a href="/1">Home</a>"#,
+            r#"<span class="sep">·</span><a href="/2">Ponies</a>"#,
+            r#"<span class="sep">·</span><a href="/3">Rarity</a></nav>"#));
+}
+
+#[test]
+fn for_separated_by() {
+    struct Crumb {
+        url: &'static str,
+        name: &'static str,
+    }
+    let crumbs = [
+        Crumb { url: "/", name: "Home" },
+        Crumb { url: "/ponies", name: "Ponies" },
+        Crumb { url: "/ponies/rarity", name: "Rarity" },
+    ];
+    let s = html! {
+        @for crumb in &crumbs {
+            a href=(crumb.url) { (crumb.name) }
+        } @separated_by {
+            " › "
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<a href="/">Home</a> › "#,
+            r#"<a href="/ponies">Ponies</a> › "#,
+            r#"<a href="/ponies/rarity">Rarity</a>"#));
+}
+
+#[test]
+fn for_sorted() {
+    use std::collections::HashMap;
+
+    let mut ponies = HashMap::new();
+    for (i, name) in ["Twilight", "Applejack", "Rarity", "Fluttershy", "Pinkie Pie", "Rainbow Dash"].iter().enumerate() {
+        ponies.insert(*name, i);
+    }
+    let render = || html! {
+        ul {
+            @for_sorted (name, id) in &ponies {
+                li { (name) ": " (id) }
+            } @sep {
+                " "
+            }
+        }
+    }.into_string();
+    let s = render();
+    assert_eq!(s, concat!(
+            "<ul><li>Applejack: 1</li> <li>Fluttershy: 3</li> <li>Pinkie Pie: 4</li> ",
+            "<li>Rainbow Dash: 5</li> <li>Rarity: 2</li> <li>Twilight: 0</li></ul>"));
+    assert_eq!(render(), s);
+    let s = html! { @for_sorted (k, v) in vec![(3, 'c'), (1, 'a'), (2, 'b')] { (k) (v) } }.into_string();
+    assert_eq!(s, "1a2b3c");
+}
+
+#[test]
+fn dl_from_pairs() {
+    let stats = vec![("Name", "Pinkie Pie".to_string()), ("Likes", "<parties>".to_string())];
+    let s = html! {
+        dl.stats {
+            @dl from &stats;
+            dt { "Age" } dd { (12) }
+        }
+        dl { @dl from [(1, 2.5)] }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<dl class="stats">"#,
+            r#"<dt>Name</dt><dd>Pinkie Pie</dd>"#,
+            r#"<dt>Likes</dt><dd>&lt;parties&gt;</dd>"#,
+            r#"<dt>Age</dt><dd>12</dd>"#,
+            r#"</dl>"#,
+            r#"<dl><dt>1</dt><dd>2.5</dd></dl>"#));
+}
+
+#[test]
+fn table_from_rows() {
+    let headers = ["Pony", "<Color>"];
+    let rows = [
+        vec!["Rarity".to_string(), "White".to_string()],
+        vec!["Pinkie & Gummy".to_string()],
+    ];
+    let s = html! {
+        @table headers=(headers) rows=(rows)
+        @table headers=(&["Empty"][..]) rows=(Vec::<Vec<String>>::new())
+    }.into_string();
+    assert_eq!(s, concat!(
+            "<table>",
+            "<thead><tr><th>Pony</th><th>&lt;Color&gt;</th></tr></thead>",
+            "<tbody>",
+            "<tr><td>Rarity</td><td>White</td></tr>",
+            "<tr><td>Pinkie &amp; Gummy</td><td></td></tr>",
+            "</tbody>",
+            "</table>",
+            "<table><thead><tr><th>Empty</th></tr></thead><tbody></tbody></table>"));
+}
+
+#[test]
+fn write_block() {
+    use std::fmt::{self, Write};
+
+    fn stars(w: &mut dyn Write, count: usize) -> fmt::Result {
+        for _ in 0..count {
+            w.write_str("<i>*</i>")?;
+        }
+        Ok(())
+    }
+
+    let rating = 3;
+    let s = html! {
+        p {
+            "Rating: "
+            @write |w| {
+                stars(w, rating)?;
+                write!(w, " ({}/5)", rating)?;
+            }
+        }
+    }.into_string();
+    assert_eq!(s, "<p>Rating: <i>*</i><i>*</i><i>*</i> (3/5)</p>");
+
+    let mut t = String::new();
+    html!(&mut t, { b { @write |out| { stars(out, 2)? } } }).unwrap();
+    assert_eq!(t, "<b><i>*</i><i>*</i></b>");
+}
+
+#[test]
+fn write_block_error() {
+    use std::fmt;
+
+    struct Failing;
+    impl fmt::Display for Failing {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            html!(f, {
+                "before"
+                @write |_w| { return Err(fmt::Error); }
+                "after"
+            })
+        }
+    }
+    let mut s = String::new();
+    assert!(fmt::write(&mut s, format_args!("{}", Failing)).is_err());
+    assert_eq!(s, "before");
+}
+
+#[test]
+fn options_with_selected() {
+    let items = [("red", "Red"), ("green", "Green & <Lime>"), ("blue\"", "Blue")];
+    let current = "green";
+    let s = html! {
+        select name="color" {
+            @options (items) selected=(current)
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<select name="color">"#,
+            r#"<option value="red">Red</option>"#,
+            r#"<option value="green" selected>Green &amp; &lt;Lime&gt;</option>"#,
+            r#"<option value="blue&quot;">Blue</option>"#,
+            "</select>"));
+}
+
+#[test]
+fn options_owned_values() {
+    let sizes = [(1, "Small".to_string()), (2, "Large".to_string())];
+    let s = html! {
+        select { @options (sizes) }
+        select { @options (sizes) selected=(2) }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<select><option value="1">Small</option><option value="2">Large</option></select>"#,
+            r#"<select><option value="1">Small</option><option value="2" selected>Large</option></select>"#));
+}
@@ -0,0 +1,46 @@
+use maud::{html, Markup, Render};
+
+#[derive(Render)]
+#[template(html = r#"
+    div.card {
+        h2 { (self.title) }
+        p { (self.body) }
+    }
+"#)]
+struct Card {
+    title: &'static str,
+    body: String,
+}
+
+#[test]
+fn derive_html() {
+    let card = Card { title: "Rarity", body: "Generous & fashionable".to_string() };
+    let s = html! {
+        main { (card) }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<main><div class="card">"#,
+            "<h2>Rarity</h2>",
+            "<p>Generous &amp; fashionable</p>",
+            "</div></main>"));
+}
+
+#[derive(Render)]
+#[template(method = "view")]
+struct Badge<T: std::fmt::Display> {
+    count: T,
+}
+
+impl<T: std::fmt::Display> Badge<T> {
+    fn view(&self) -> Markup {
+        html! { span.badge { (self.count) } }
+    }
+}
+
+#[test]
+fn derive_method() {
+    let badge = Badge { count: 3 };
+    assert_eq!(badge.render().into_string(), r#"<span class="badge">3</span>"#);
+    let s = html! { @for badge in &[Badge { count: "<1>" }] { (badge) } }.into_string();
+    assert_eq!(s, r#"<span class="badge">&lt;1&gt;</span>"#);
+}
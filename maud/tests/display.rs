@@ -0,0 +1,38 @@
+#![feature(specialization)]
+#![allow(incomplete_features)]
+
+use maud::{display, html, Render};
+use std::fmt;
+
+struct Pinkie;
+
+impl fmt::Display for Pinkie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<Pinkie Pie>")
+    }
+}
+
+impl Render for Pinkie {
+    fn render_to(&self, w: &mut String) {
+        w.push_str("<b>Pinkie Pie</b>");
+    }
+}
+
+#[test]
+fn splice_prefers_render() {
+    let s = html!((Pinkie)).into_string();
+    assert_eq!(s, "<b>Pinkie Pie</b>");
+}
+
+#[test]
+fn display_forces_escaped_text() {
+    let s = html!((display(Pinkie))).into_string();
+    assert_eq!(s, "&lt;Pinkie Pie&gt;");
+}
+
+#[test]
+fn display_by_reference() {
+    let pinkie = Pinkie;
+    let s = html!((pinkie) " " (display(&pinkie))).into_string();
+    assert_eq!(s, "<b>Pinkie Pie</b> &lt;Pinkie Pie&gt;");
+}
@@ -0,0 +1,15 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        <div class="greeting" data-id="7">
+            "Hello"
+            <br/>
+            "World"
+        </div>
+        <input type="checkbox" checked>
+        p { @nope }
+    };
+}
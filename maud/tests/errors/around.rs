@@ -0,0 +1,12 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @around { "No wrapper" }
+    };
+    html! {
+        @around card
+    };
+}
@@ -0,0 +1,10 @@
+extern crate maud;
+
+use maud::html_chunks;
+
+fn main() {
+    let _ = html_chunks! {
+        @let name = "Ferris";
+        p { (name) }
+    };
+}
@@ -0,0 +1,11 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    let flags = [("cute", true)];
+    html! {
+        p title=toggle[&flags] { "Squee!" }
+        p.fluffy class=toggle[&flags] { "Squee!" }
+    };
+}
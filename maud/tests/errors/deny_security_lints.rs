@@ -0,0 +1,11 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html!(deny(inline_event_handlers), warn(unsafe_urls), {
+        body onload="init()" {
+            a href=" JavaScript:run()" { "Run" }
+        }
+    });
+}
@@ -0,0 +1,16 @@
+extern crate maud;
+
+use maud::Render;
+
+#[derive(Render)]
+struct NoTemplate;
+
+#[derive(Render)]
+#[template(markdown = "# Hi")]
+struct UnknownKind;
+
+#[derive(Render)]
+#[template(method = "not a method")]
+struct BadMethod;
+
+fn main() {}
@@ -0,0 +1,13 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    let mut total = 0;
+    html! {
+        @do;
+    };
+    html! {
+        @do total += 1
+    };
+}
@@ -0,0 +1,12 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        footer { @env CARGO_PKG_VERSION }
+    };
+    html! {
+        footer { @env "MAUD_SURELY_NOT_SET" }
+    };
+}
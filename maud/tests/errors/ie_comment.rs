@@ -0,0 +1,15 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @ie_comment { "No condition" }
+    };
+    html! {
+        @ie_comment "lt IE 9]><!--" { "Breaks out" }
+    };
+    html! {
+        @ie_comment "IE" p { "No body" }
+    };
+}
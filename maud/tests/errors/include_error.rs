@@ -0,0 +1,9 @@
+extern crate maud;
+
+use maud::html_include;
+
+fn main() {
+    let name = "Ferris";
+    html_include!("include_error.maud");
+    html_include!("missing.maud");
+}
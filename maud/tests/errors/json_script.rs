@@ -0,0 +1,15 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @json_script id="data"
+    };
+    html! {
+        @json_script type="text/plain" (1)
+    };
+    html! {
+        p title=@json_script (1) {}
+    };
+}
@@ -0,0 +1,13 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        p { @foo }
+        div class=, { "Pinkie Pie" }
+        span { "Rarity" }
+        @ 42
+        p { "Applejack" }
+    };
+}
@@ -0,0 +1,13 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    let items = [("a", "A")];
+    html! {
+        select { @options items }
+    };
+    html! {
+        select { @options (items) selected="a" }
+    };
+}
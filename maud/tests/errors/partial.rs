@@ -0,0 +1,18 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @partial "../templates/partials/missing.maud"
+    };
+    html! {
+        @partial "../templates/partials/recursive.maud"
+    };
+    html! {
+        @partial "../templates/partials/roundabout.maud"
+    };
+    html! {
+        @partial header
+    };
+}
@@ -0,0 +1,15 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @pi
+    };
+    html! {
+        @pi "?xml"
+    };
+    html! {
+        @pi "xml version=\"1.0\"?>"
+    };
+}
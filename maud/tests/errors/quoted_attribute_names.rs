@@ -0,0 +1,9 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        button "@click"="a" "on click"="b" "x>y"? "@click"="c" { "Toggle" }
+    };
+}
@@ -0,0 +1,9 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @spaceless p { "Not a block" }
+    };
+}
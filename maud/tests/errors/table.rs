@@ -0,0 +1,12 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @table rows=(vec![vec!["a"]])
+    };
+    html! {
+        @table headers=(["a"])
+    };
+}
@@ -0,0 +1,11 @@
+extern crate maud;
+
+use maud::{template, Markup};
+
+template! {
+    fn row<F: Fn() -> u8>(cell: F) -> Markup {
+        td { (cell()) }
+    }
+}
+
+fn main() {}
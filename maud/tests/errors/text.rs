@@ -0,0 +1,14 @@
+// normalize-stderr-test "src/lib.rs:\d+:\d+" -> "src/lib.rs:LL:CC"
+extern crate maud;
+
+use maud::{html, PreEscaped};
+
+fn main() {
+    let comment = "<b>hi</b>";
+    html! {
+        @text comment
+    };
+    html! {
+        @text (PreEscaped("<b>"))
+    };
+}
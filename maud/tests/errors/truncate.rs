@@ -0,0 +1,11 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    let body = "Ponies are great";
+    html! {
+        @truncate (body)
+        @truncate 10 body
+    };
+}
@@ -0,0 +1,15 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    html! {
+        @write
+    };
+    html! {
+        @write |w|
+    };
+    html! {
+        @write { "no writer" }
+    };
+}
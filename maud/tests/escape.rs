@@ -0,0 +1,161 @@
+use maud::{html, PreEscaped, Render};
+
+#[test]
+fn custom_escaper() {
+    let mut s = String::new();
+    "café <&>".render_escaped_to(&mut s, &maud::escape_ascii);
+    assert_eq!(s, "caf&#233; &lt;&amp;&gt;");
+}
+
+#[test]
+fn custom_escaper_display() {
+    let mut s = String::new();
+    'é'.render_escaped_to(&mut s, &maud::escape_ascii);
+    String::from("ü").render_escaped_to(&mut s, &maud::escape_ascii);
+    maud::display(1.5).render_escaped_to(&mut s, &maud::escape_ascii);
+    assert_eq!(s, "&#233;&#252;1.5");
+}
+
+#[test]
+fn custom_escaper_closure() {
+    let mut s = String::new();
+    "Pinkie Pie".render_escaped_to(&mut s, &|text: &str, buffer: &mut String| {
+        buffer.push_str(&text.to_uppercase());
+    });
+    assert_eq!(s, "PINKIE PIE");
+}
+
+#[test]
+fn custom_escaper_skips_markup() {
+    let mut s = String::new();
+    PreEscaped("<b>é</b>").render_escaped_to(&mut s, &maud::escape_ascii);
+    html! { i { "é" } }.render_escaped_to(&mut s, &maud::escape_ascii);
+    assert_eq!(s, "<b>é</b><i>é</i>");
+}
+
+/// Escapes `@` as well as the usual HTML characters.
+fn at_escape(text: &str, buffer: &mut String) {
+    for c in text.chars() {
+        match c {
+            '@' => buffer.push_str("&#64;"),
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            '"' => buffer.push_str("&quot;"),
+            c => buffer.push(c),
+        }
+    }
+}
+
+#[test]
+fn escaper_option() {
+    let email = "pinkie@example.com";
+    let s = html!(escaper = at_escape, {
+        p title="me@home" { "Mail " (email) " <@>" }
+        (PreEscaped("<b>@</b>"))
+    }).into_string();
+    assert_eq!(s, concat!(
+        r#"<p title="me&#64;home">Mail pinkie&#64;example.com &lt;&#64;&gt;</p>"#,
+        "<b>@</b>",
+    ));
+}
+
+#[test]
+fn escaper_option_closure() {
+    let shout = |text: &str, buffer: &mut String| buffer.push_str(&text.to_uppercase());
+    let name = "Pinkie Pie";
+    let s = html!(escaper = shout, { p { "hi, " (name) } }).into_string();
+    assert_eq!(s, "<p>HI, PINKIE PIE</p>");
+}
+
+#[test]
+fn escaper_option_classes() {
+    let class = "café";
+    let enabled = true;
+    let s = html!(escaper = maud::escape_ascii, {
+        p.(class) {}
+        p.naïve[enabled].(class) {}
+    }).into_string();
+    assert_eq!(s, r#"<p class="caf&#233;"></p><p class="na&#239;ve caf&#233;"></p>"#);
+}
+
+#[test]
+fn escaper_option_nested_markup() {
+    // Markup is escaped when it's built, so only the outer block's text
+    // goes through the escaper
+    let inner = html! { b { "é" } };
+    let s = html!(escaper = maud::escape_ascii, { p { "é" (inner) } }).into_string();
+    assert_eq!(s, "<p>&#233;<b>é</b></p>");
+}
+
+#[test]
+fn escaper_option_writer() {
+    let mut s = String::new();
+    let email = "pinkie@example.com";
+    html!(&mut s, escaper = at_escape, { a href={ "mailto:" (email) } { "@" } }).unwrap();
+    assert_eq!(s, r#"<a href="mailto:pinkie&#64;example.com">&#64;</a>"#);
+}
+
+#[test]
+fn escape_chunks() {
+    let input = "Pinkie & \"Rarity\"";
+    let chunks = maud::escape_chunks(input).collect::<Vec<_>>();
+    assert_eq!(chunks, ["Pinkie ", "&amp;", " ", "&quot;", "Rarity", "&quot;"]);
+    // The clean runs are borrowed from the input
+    assert_eq!(chunks[0].as_ptr(), input.as_ptr());
+}
+
+#[test]
+fn text_and_attribute_escaping() {
+    // Text and attribute values share one escaper. It escapes `"`, which is
+    // all a double-quoted attribute needs, and `<` and `>`, which are
+    // harmless there, so the same value is safe in both places.
+    let value = r#"a < b & "c" 'd'"#;
+    let s = html! { p title=(value) { (value) } }.into_string();
+    assert_eq!(s, concat!(
+        r#"<p title="a &lt; b &amp; &quot;c&quot; 'd'">"#,
+        r#"a &lt; b &amp; &quot;c&quot; 'd'</p>"#,
+    ));
+}
+
+#[test]
+fn query_string_ampersands() {
+    // Every `&` in an attribute is written as `&amp;`, whether it comes
+    // from a literal or a splice
+    let url = "/search?q=ponies&page=2&sort=new";
+    let s = html! {
+        a href=(url) {}
+        a href="/search?q=ponies&page=2" {}
+        a href={ "/search?q=" (format_args!("{}&page={}", "ponies", 3)) } {}
+    }.into_string();
+    assert_eq!(s, concat!(
+        r#"<a href="/search?q=ponies&amp;page=2&amp;sort=new"></a>"#,
+        r#"<a href="/search?q=ponies&amp;page=2"></a>"#,
+        r#"<a href="/search?q=ponies&amp;page=3"></a>"#,
+    ));
+}
+
+#[test]
+fn display_with_write_char() {
+    use std::fmt::{self, Write};
+    struct Arrow;
+    impl fmt::Display for Arrow {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_char('<')?;
+            f.write_char('-')?;
+            f.write_char('&')
+        }
+    }
+    let s = html!(p { (Arrow) } span title=(Arrow) {}).into_string();
+    assert_eq!(s, r#"<p>&lt;-&amp;</p><span title="&lt;-&amp;"></span>"#);
+}
+
+#[test]
+fn ascii_only_output() {
+    let name = "Zoë";
+    let s = html!(escaper = maud::escape_ascii, {
+        p title="café ☕" { "Hi, " (name) " — " b { "<naïve>" } }
+    }).into_string();
+    assert_eq!(s, r#"<p title="caf&#233; &#9749;">Hi, Zo&#235; &#8212; <b>&lt;na&#239;ve&gt;</b></p>"#);
+    assert!(s.is_ascii());
+}
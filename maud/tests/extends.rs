@@ -0,0 +1,123 @@
+use maud::{html, Markup};
+
+#[derive(Default)]
+struct Page {
+    title: Option<Markup>,
+    head_extra: Markup,
+    content: Markup,
+}
+
+fn layout(page: Page) -> Markup {
+    html! {
+        html {
+            head {
+                title { (page.title.unwrap_or_else(|| html! { "Ponyville" })) }
+                (page.head_extra)
+            }
+            body { (page.content) }
+        }
+    }
+}
+
+#[test]
+fn default_blocks() {
+    let s = html! {
+        @extends layout {
+            @block content { p { "Hello!" } }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            "<html><head><title>Ponyville</title></head>",
+            "<body><p>Hello!</p></body></html>"));
+}
+
+#[test]
+fn override_blocks() {
+    let user = "Twilight";
+    let s = html! {
+        @extends layout {
+            @block title { "Dashboard" }
+            @block head_extra { link rel="stylesheet" href="dash.css"; }
+            @block content {
+                @let greeting = "Welcome back, ";
+                h1 { (greeting) (user) }
+            }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            "<html><head><title>Dashboard</title>",
+            r#"<link rel="stylesheet" href="dash.css">"#,
+            "</head><body><h1>Welcome back, Twilight</h1></body></html>"));
+}
+
+#[test]
+fn extends_inside_markup() {
+    mod layouts {
+        use maud::{html, Markup};
+
+        #[derive(Default)]
+        pub struct Card {
+            pub header: Markup,
+            pub body: Markup,
+        }
+
+        pub fn card(card: Card) -> Markup {
+            html! { .card { header { (card.header) } (card.body) } }
+        }
+    }
+
+    let s = html! {
+        @for name in &["Rarity", "Applejack"] {
+            @extends layouts::card {
+                @block header { (name) }
+            }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<div class="card"><header>Rarity</header></div>"#,
+            r#"<div class="card"><header>Applejack</header></div>"#));
+}
+
+#[test]
+fn extends_with_writer() {
+    let mut s = String::new();
+    html!(&mut s, {
+        @extends layout {
+            @block title { "<Tab>" }
+        }
+    }).unwrap();
+    assert_eq!(s, "<html><head><title>&lt;Tab&gt;</title></head><body></body></html>");
+}
+
+fn card_wrapper(inner: Markup) -> Markup {
+    html! { div.card { (inner) } }
+}
+
+fn section(title: &str) -> impl Fn(Markup) -> Markup + '_ {
+    move |inner| html! { section { h2 { (title) } (inner) } }
+}
+
+#[test]
+fn around() {
+    let name = "Rarity";
+    let s = html! {
+        @around card_wrapper {
+            p { "Hello, " (name) "!" }
+        }
+        @around section("<Ponies>") {
+            @for pony in &["Applejack", "Fluttershy"] {
+                @around card_wrapper { (pony) }
+            }
+        }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<div class="card"><p>Hello, Rarity!</p></div>"#,
+            "<section><h2>&lt;Ponies&gt;</h2>",
+            r#"<div class="card">Applejack</div>"#,
+            r#"<div class="card">Fluttershy</div>"#,
+            "</section>"));
+
+    let mut t = String::new();
+    html!(&mut t, { main { @around card_wrapper { "Writer" } } }).unwrap();
+    assert_eq!(t, r#"<main><div class="card">Writer</div></main>"#);
+}
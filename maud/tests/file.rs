@@ -0,0 +1,58 @@
+use maud::html;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Returns a fresh directory for a test to write into.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("maud-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn render_to_file() {
+    let dir = test_dir("render_to_file");
+    let path = dir.join("ponies").join("index.html");
+    let name = "Rarity";
+    maud::render_to_file(|w| html!(w, { h1 { (name) } }), &path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "<h1>Rarity</h1>");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn render_markup_to_file() {
+    let dir = test_dir("render_markup_to_file");
+    let path = dir.join("page.html");
+    let page = html! { p { "Hi!" } };
+    maud::render_to_file(|w| w.write_str(&page.0), &path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "<p>Hi!</p>");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn render_to_path_atomic() {
+    let dir = test_dir("render_to_path_atomic");
+    let path = dir.join("index.html");
+    maud::render_to_path_atomic(|w| html!(w, { "first" }), &path).unwrap();
+    maud::render_to_path_atomic(|w| html!(w, { "second" }), &path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn render_to_path_atomic_failure() {
+    let dir = test_dir("render_to_path_atomic_failure");
+    let path = dir.join("index.html");
+    maud::render_to_path_atomic(|w| html!(w, { "old" }), &path).unwrap();
+    let result = maud::render_to_path_atomic(|w| {
+        html!(w, { "partial" })?;
+        Err(fmt::Error)
+    }, &path);
+    assert!(result.is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,41 @@
+use maud::{html, html_include};
+
+#[test]
+fn include() {
+    let name = "Ferris";
+    let result = html_include!("templates/greeting.maud");
+    assert_eq!(result.into_string(), r#"<p class="greeting">Hello, Ferris!</p>"#);
+}
+
+#[test]
+fn include_control_structures() {
+    let items = ["one", "two"];
+    let result = html_include!("templates/list.maud");
+    assert_eq!(result.into_string(), "<ul><li>one</li><li>two</li></ul>");
+}
+
+#[test]
+fn include_in_splice() {
+    let name = "<Ferris>";
+    let result = html! {
+        main { (html_include!("templates/greeting.maud")) }
+    };
+    assert_eq!(
+        result.into_string(),
+        r#"<main><p class="greeting">Hello, &lt;Ferris&gt;!</p></main>"#,
+    );
+}
+
+#[test]
+fn partial() {
+    let title = "Ponyville <News>";
+    let links = [("/", "Home"), ("/about", "About")];
+    let result = html! {
+        @partial "templates/partials/header.maud"
+        main { "Content" }
+    };
+    assert_eq!(result.into_string(), concat!(
+            "<header><h1>Ponyville &lt;News&gt;</h1>",
+            r#"<nav><a href="/">Home</a><a href="/about">About</a></nav></header>"#,
+            "<main>Content</main>"));
+}
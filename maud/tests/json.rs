@@ -0,0 +1,45 @@
+#![cfg(feature = "json")]
+
+use maud::{html, Json};
+use serde_derive::Serialize;
+
+#[derive(Serialize)]
+struct Pony {
+    name: &'static str,
+    bio: &'static str,
+    friends: Vec<&'static str>,
+}
+
+#[test]
+fn json_script() {
+    let pony = Pony {
+        name: "Pinkie Pie",
+        bio: "</script><script>alert('party') && \"fun\"</script>",
+        friends: vec!["Gummy"],
+    };
+    let s = html! {
+        @json_script id="pony-data" (pony)
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<script type="application/json" id="pony-data">"#,
+            r#"{"name":"Pinkie Pie","#,
+            r#""bio":"\u003c/script\u003e\u003cscript\u003ealert('party') \u0026\u0026 \"fun\"\u003c/script\u003e","#,
+            r#""friends":["Gummy"]}"#,
+            "</script>"));
+    assert!(!s[..s.len() - "</script>".len()].contains("</script>"));
+}
+
+#[test]
+fn json_round_trips() {
+    let value = vec!["<a> & <b>"];
+    let s = html!((Json(&value))).into_string();
+    let parsed: Vec<String> = serde_json::from_str(&s).unwrap();
+    assert_eq!(parsed, value);
+}
+
+#[test]
+fn json_script_expression() {
+    let counts = [1, 2, 3];
+    let s = html!(@json_script (counts.iter().map(|n| n * 2).collect::<Vec<_>>())).into_string();
+    assert_eq!(s, r#"<script type="application/json">[2,4,6]</script>"#);
+}
@@ -1,6 +1,4 @@
-#![feature(proc_macro_hygiene)]
-
-use maud::{self, html};
+use maud::{self, html, PreEscaped};
 
 #[test]
 fn issue_13() {
@@ -80,3 +78,355 @@ fn issue_97() {
 
     assert_eq!(html!((Pinkie)).into_string(), "42");
 }
+
+#[test]
+fn return_markup_from_locals() {
+    fn header(title: &str) -> maud::Markup {
+        let title = title.to_uppercase();
+        let subtitle = format!("{} chars", title.len());
+        // `html!` renders eagerly, so the result owns its contents and
+        // doesn't borrow `title` or `subtitle`
+        html! {
+            h1 { (title) }
+            p { (subtitle) }
+        }
+    }
+
+    let header = header("pinkie");
+    let s = html! {
+        header { (header) }
+    }.into_string();
+    assert_eq!(s, "<header><h1>PINKIE</h1><p>6 chars</p></header>");
+}
+
+#[test]
+fn html_string() {
+    let s: String = maud::html_string! { p { "x" } };
+    assert_eq!(s, "<p>x</p>");
+    let name = "<Pinkie>";
+    let s = maud::html_string!(minify = true, { p.intro { (name) } });
+    assert_eq!(s, "<p class=intro>&lt;Pinkie&gt;</p>");
+}
+
+#[test]
+fn into_reader() {
+    use std::io::Read;
+    let markup = || html! { p { "Hi, " b { "Pinkie Pie" } "!" } };
+    let mut s = String::new();
+    markup().into_reader().read_to_string(&mut s).unwrap();
+    assert_eq!(s, markup().into_string());
+}
+
+#[test]
+fn into_attribute_string() {
+    assert_eq!(html! { b { "x" } }.into_attribute_string(), "&lt;b&gt;x&lt;/b&gt;");
+    let tooltip = html! { span.hint title="\"Hi\"" { "Fish & chips" } };
+    let s = html! { abbr title=(PreEscaped(tooltip.into_attribute_string())) { "?" } }.into_string();
+    assert_eq!(s, concat!(
+            r#"<abbr title="&lt;span class=&quot;hint&quot; title=&quot;&amp;quot;Hi&amp;quot;&quot;&gt;"#,
+            r#"Fish &amp;amp; chips&lt;/span&gt;">?</abbr>"#));
+}
+
+#[test]
+fn into_text() {
+    assert_eq!(html! { p { "Hello " b { "world" } } }.into_text(), "Hello world");
+    let name = "Rarity & \"Sweetie Belle\" <sisters>";
+    let markup = html! {
+        (maud::DOCTYPE)
+        html {
+            head {
+                title { "Ponies" }
+                style { "p > b { color: red }" }
+                script { (PreEscaped("if (a < b) {}")) }
+            }
+            body {
+                h1 title="Not text" { (name) }
+                (PreEscaped("<!-- hidden -->&#233;&#x2014;&copy;&nope;"))
+                br;
+                a href="/?a=1&b=2" { "It's" }
+            }
+        }
+    };
+    assert_eq!(markup.into_text(), "PoniesRarity & \"Sweetie Belle\" <sisters>é—&copy;&nope;It's");
+}
+
+#[test]
+fn into_text_bare_less_than() {
+    let markup = html! {
+        p { (PreEscaped("1 < 2 and 3 <4")) " but " (PreEscaped("a<b>c")) }
+        p { (PreEscaped("trailing <")) }
+    };
+    assert_eq!(markup.into_text(), "1 < 2 and 3 <4 but actrailing <");
+}
+
+#[test]
+fn write_attr() {
+    use maud::Render;
+    use maud::rt::write_attr;
+
+    let mut s = String::new();
+    write_attr(&mut s, "title", "a\"b");
+    assert_eq!(s, r#" title="a&quot;b""#);
+
+    struct Link<'a> {
+        href: &'a str,
+        title: &'a str,
+        tab_index: i32,
+    }
+    impl<'a> Render for Link<'a> {
+        fn render_to(&self, buffer: &mut String) {
+            buffer.push_str("<a");
+            write_attr(buffer, "href", self.href);
+            write_attr(buffer, "title", self.title);
+            write_attr(buffer, "tabindex", &self.tab_index);
+            buffer.push_str("></a>");
+        }
+    }
+    let (href, title, tab_index) = ("/?a=1&b=<2>", "It's \"Rarity\"", -1);
+    assert_eq!(
+        Link { href, title, tab_index }.render().into_string(),
+        html!(a href=(href) title=(title) tabindex=(tab_index) {}).into_string());
+}
+
+#[test]
+fn render_markup_repeatedly() {
+    use maud::Render;
+    let footer = html! { footer { "© " (2019) " <ponies>" } };
+    let first = html! { main { "A" } (footer) };
+    let second = html! { main { "B" } (footer) (footer) };
+    assert_eq!(first.into_string(), r#"<main>A</main><footer>© 2019 &lt;ponies&gt;</footer>"#);
+    assert_eq!(second.into_string(), concat!(
+        r#"<main>B</main>"#,
+        r#"<footer>© 2019 &lt;ponies&gt;</footer>"#,
+        r#"<footer>© 2019 &lt;ponies&gt;</footer>"#));
+    let cached = footer.clone();
+    let mut buffer = String::new();
+    cached.render_to(&mut buffer);
+    cached.render_to(&mut buffer);
+    assert_eq!(buffer, footer.into_string().repeat(2));
+}
+
+#[test]
+fn chunks() {
+    let ponies = ["Rarity", "Applejack"];
+    let chunks = maud::html_chunks! {
+        h1 { "Ponies" }
+        {
+            @let count = ponies.len();
+            p { (count) " ponies" }
+        }
+        @for pony in &ponies {
+            li { (pony) }
+        }
+        "fin"
+    }.collect::<Vec<_>>();
+    assert_eq!(chunks, [
+        "<h1>Ponies</h1>",
+        "<p>2 ponies</p>",
+        "<li>Rarity</li><li>Applejack</li>",
+        "fin",
+    ]);
+    let s = html! {
+        h1 { "Ponies" }
+        p { (ponies.len()) " ponies" }
+        @for pony in &ponies {
+            li { (pony) }
+        }
+        "fin"
+    }.into_string();
+    assert_eq!(chunks.concat(), s);
+}
+
+#[test]
+fn chunks_skip_empty() {
+    let chunks = maud::html_chunks! {
+        @if false { "nope" }
+        p { "yep" }
+    };
+    assert_eq!(chunks.collect::<Vec<_>>(), ["<p>yep</p>"]);
+}
+
+#[test]
+fn chunks_are_lazy() {
+    use std::cell::Cell;
+    let rendered = Cell::new(0);
+    let count = || {
+        rendered.set(rendered.get() + 1);
+        rendered.get()
+    };
+    let mut chunks = maud::html_chunks! {
+        p { (count()) }
+        p { (count()) }
+        p { (count()) }
+    };
+    assert_eq!(rendered.get(), 0);
+    assert_eq!(chunks.next().as_deref(), Some("<p>1</p>"));
+    assert_eq!(rendered.get(), 1);
+    assert_eq!(chunks.next().as_deref(), Some("<p>2</p>"));
+    assert_eq!(rendered.get(), 2);
+    drop(chunks);
+    // The last node is never rendered
+    assert_eq!(rendered.get(), 2);
+}
+
+#[test]
+fn concat_markup() {
+    let header = html! { h1 { "Ponies" } };
+    let body = html! { p { "Twilight & Spike" } };
+    let footer = html! { footer { "Fin" } };
+    let expected = html! {
+        h1 { "Ponies" }
+        p { "Twilight & Spike" }
+        footer { "Fin" }
+    }.into_string();
+
+    let added = header.clone() + body.clone() + footer.clone();
+    assert_eq!(added.into_string(), expected);
+
+    let concatenated = maud::concat(vec![header, body, footer]);
+    assert_eq!(concatenated.into_string(), expected);
+}
+
+#[test]
+fn collect_markup() {
+    use maud::{Markup, Render};
+    use std::iter::FromIterator;
+
+    struct Pony {
+        name: &'static str,
+    }
+    impl Render for Pony {
+        fn render(&self) -> Markup {
+            html! { li { (self.name) } }
+        }
+    }
+
+    // As if fetched by an async handler before rendering
+    let ponies = vec![Pony { name: "Applejack" }, Pony { name: "<Rarity>" }];
+    let looped = html! { ul { @for pony in &ponies { (pony) } } }.into_string();
+    let list = Markup::from_iter(ponies);
+    assert_eq!(list.clone().into_string(), "<li>Applejack</li><li>&lt;Rarity&gt;</li>");
+    assert_eq!(html! { ul { (list) } }.into_string(), looped);
+
+    let names: Markup = ["a", "<b>"].iter().collect();
+    assert_eq!(names.into_string(), "a&lt;b&gt;");
+}
+
+#[test]
+fn concat_empty() {
+    assert_eq!(maud::concat(Vec::<maud::Markup>::new()).into_string(), "");
+    let s = maud::empty() + html! { br; } + maud::empty();
+    assert_eq!(s.into_string(), "<br>");
+}
+
+#[test]
+fn add_assign_markup() {
+    let mut page = maud::empty();
+    for pony in &["Rarity", "Applejack"] {
+        page += html! { li { (pony) } };
+    }
+    page += maud::PreEscaped("<!-- end -->");
+    assert_eq!(page.into_string(), "<li>Rarity</li><li>Applejack</li><!-- end -->");
+}
+
+#[test]
+fn assert_markup_eq_passes() {
+    maud::assert_markup_eq!(html! { p { "Rarity" } }, "<p>Rarity</p>");
+    maud::assert_markup_eq!(maud::PreEscaped("<br>"), "<br>",);
+}
+
+#[test]
+#[should_panic(expected = "markup differs from expected at byte 3")]
+fn assert_markup_eq_fails() {
+    maud::assert_markup_eq!(html! { p { "Rarity" } }, "<p>Applejack</p>");
+}
+
+maud::render_test!(render_test_passes, {
+    ul { @for pony in &["Rarity", "Applejack"] { li { (pony) } } }
+}, "<ul><li>Rarity</li><li>Applejack</li></ul>");
+
+maud::render_test!(
+    #[should_panic(expected = "markup differs from expected at byte 3")]
+    render_test_fails,
+    { p { "Rarity" } },
+    "<p>Applejack</p>",
+);
+
+#[test]
+fn static_capacity() {
+    let s = html! {
+        div.card {
+            h1 { "Pinkie Pie" }
+            p { "Likes parties, cupcakes, and practical jokes." }
+        }
+    }.into_string();
+    assert!(s.capacity() >= s.len());
+}
+
+#[test]
+fn empty_literals_write_nothing() {
+    fn shout(text: &str, buffer: &mut String) {
+        buffer.push_str(&text.to_uppercase());
+    }
+    let s = html!(escaper = shout, {
+        p { "" "hi" "" }
+    }).into_string();
+    assert_eq!(s, "<p>HI</p>");
+    let s = html!(escaper = shout, { p { "" } }).into_string();
+    // No runtime escaping left, so the template counts as fully static
+    assert_eq!(s, "<p></p>");
+    assert!(s.capacity() >= s.len());
+}
+
+#[test]
+fn dynamic_capacity() {
+    let name = "Pinkie Pie";
+    let s = html! {
+        p { "A very long static introduction that goes on for a while: " (name) }
+    }.into_string();
+    // Reserved up front, so the static parts never reallocate
+    assert!(s.capacity() >= "<p>A very long static introduction that goes on for a while: </p>".len());
+}
+
+#[test]
+fn into_string_checked() {
+    let name = "<Pinkie Pie>";
+    let markup = html! {
+        (maud::DOCTYPE)
+        p title=(name) { (name) br; (PreEscaped("<b>fine</b>")) }
+    };
+    assert_eq!(markup.clone().into_string_checked(), markup.into_string());
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "found `</div>` where `</b>` was expected"))]
+fn into_string_checked_mismatched() {
+    html!(div { (PreEscaped("<b>bold")) }).into_string_checked();
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "`<em>` is never closed"))]
+fn into_string_checked_unclosed() {
+    html!((PreEscaped("<em>")) p { "Hi" }).into_string_checked();
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "unterminated tag"))]
+fn into_string_checked_unterminated_quote() {
+    html!(p { (PreEscaped(r#"<a href="/>"#)) }).into_string_checked();
+}
+
+#[test]
+fn wrap_markup() {
+    let field = html! { input name="email" value="<oops>"; };
+    let has_error = true;
+    let field = if has_error {
+        field.wrap("div", &[("class", "field error"), ("title", "\"email\" is required")])
+    } else {
+        field
+    };
+    assert_eq!(field.into_string(), concat!(
+            r#"<div class="field error" title="&quot;email&quot; is required">"#,
+            r#"<input name="email" value="&lt;oops&gt;">"#,
+            "</div>"));
+}
@@ -0,0 +1,124 @@
+use maud::{html, Element, Markup, Node, PreEscaped, DOCTYPE};
+
+#[test]
+fn round_trip() {
+    let page = html! {
+        (DOCTYPE)
+        html {
+            head { script src="/app.js" {} style { "p > b { color: red }" } }
+            body {
+                p.intro title="\"Hi\" & bye" { "1 < 2 " b { "&" } br; input disabled?; }
+                "<!-- not a comment -->"
+                (PreEscaped("<!-- a comment -->"))
+            }
+        }
+    };
+    let expected = page.clone().into_string();
+    let nodes = page.to_nodes();
+    assert_eq!(nodes.into_iter().collect::<Markup>().into_string(), expected);
+}
+
+#[test]
+fn add_nonce_to_scripts() {
+    let page = html! {
+        head {
+            script src="/app.js" {}
+            script { (PreEscaped("console.log('<hi>')")) }
+        }
+        body { p { "No scripts here" } }
+    };
+    let mut nodes = page.to_nodes();
+    for node in &mut nodes {
+        node.walk_mut(&mut |node| {
+            if let Node::Element(ref mut element) = *node {
+                if element.name == "script" {
+                    element.set_attr("nonce", "r4nd0m");
+                }
+            }
+        });
+    }
+    let page: Markup = nodes.into_iter().collect();
+    assert_eq!(page.into_string(), concat!(
+            r#"<head><script src="/app.js" nonce="r4nd0m"></script>"#,
+            r#"<script nonce="r4nd0m">console.log('<hi>')</script></head>"#,
+            "<body><p>No scripts here</p></body>"));
+}
+
+#[test]
+fn attributes() {
+    let nodes = PreEscaped(r#"<a href=/about/ title='It&#39;s "me"' download>About</a><br/>"#).to_nodes();
+    let link = match nodes[0] {
+        Node::Element(ref element) => element,
+        _ => panic!("expected an element"),
+    };
+    assert_eq!(link.attr("href"), Some("/about/"));
+    assert_eq!(link.attr("TITLE"), Some(r#"It's "me""#));
+    assert_eq!(link.attr("download"), Some(""));
+    assert_eq!(link.attr("rel"), None);
+    assert_eq!(nodes[1], Node::Element(Element {
+        name: "br".to_string(),
+        attrs: vec![],
+        children: None,
+        self_closing: true,
+    }));
+}
+
+#[test]
+fn mismatched_tags() {
+    let nodes = PreEscaped("<div><p>Hi</div></span>").to_nodes();
+    let markup: Markup = nodes.into_iter().collect();
+    assert_eq!(markup.into_string(), "<div><p>Hi</p></div></span>");
+}
+
+#[test]
+fn spliced_text_is_merged() {
+    // The tree is read back from the output, so a splice isn't a node of
+    // its own
+    let name = "Rarity";
+    let nodes = html! { p { "Hello, " (name) "!" } }.to_nodes();
+    match nodes.as_slice() {
+        [Node::Element(Element { children: Some(ref children), .. })] => {
+            assert_eq!(children, &[Node::Text("Hello, Rarity!".to_string())]);
+        },
+        _ => panic!("expected one element, got {:?}", nodes),
+    }
+}
+
+#[test]
+fn map_text_uppercase() {
+    let name = "<pinkie> & co";
+    let page = html! {
+        h1 title="leave me" { "Hello, " (name) }
+        script { (PreEscaped("var x = 'lower';")) }
+        p { "it's " em { "fine" } }
+    };
+    let page = page.map_text(|text| text.to_uppercase().into());
+    assert_eq!(page.into_string(), concat!(
+            r#"<h1 title="leave me">HELLO, &lt;PINKIE&gt; &amp; CO</h1>"#,
+            "<script>var x = 'lower';</script>",
+            "<p>IT'S <em>FINE</em></p>"));
+}
+
+#[test]
+fn map_text_borrowed() {
+    let page = html! { p { "darn it" } p { "hello" } };
+    let page = page.map_text(|text| match text {
+        "darn it" => "d*** it".into(),
+        text => text.into(),
+    });
+    assert_eq!(page.into_string(), "<p>d*** it</p><p>hello</p>");
+}
+
+#[test]
+fn map_text_with_attrs() {
+    let page = html! {
+        a href="/about" class="nav" title="About us" { "About" }
+        img src="/pony.png" alt="A <pony> & friend";
+        input placeholder="Name" value="keep";
+    };
+    let page = page.map_text_with_attrs(|text| text.to_uppercase().into());
+    assert_eq!(page.into_string(), concat!(
+            r#"<a href="/about" class="nav" title="ABOUT US">ABOUT</a>"#,
+            r#"<img src="/pony.png" alt="A &lt;PONY&gt; &amp; FRIEND">"#,
+            r#"<input placeholder="NAME" value="keep">"#));
+}
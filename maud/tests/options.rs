@@ -0,0 +1,112 @@
+use maud::html;
+use std::fmt;
+
+#[test]
+fn minify_unquoted_attributes() {
+    let s = html!(minify = true, {
+        div class="foo" {}
+        div class="a b" {}
+        a href="page-2.html" title="" { "Next" }
+    }).into_string();
+    assert_eq!(s, concat!(
+            r#"<div class=foo></div>"#,
+            r#"<div class="a b"></div>"#,
+            r#"<a href=page-2.html title="">Next</a>"#));
+}
+
+#[test]
+fn minify_class_shorthand() {
+    let s = html!(minify = true, { p.intro { "Hi" } p.big.intro { "Hi" } }).into_string();
+    assert_eq!(s, r#"<p class=intro>Hi</p><p class="big intro">Hi</p>"#);
+}
+
+#[test]
+fn minify_keeps_quotes_on_splices() {
+    let class = "foo";
+    let s = html!(minify = true, { div class=(class) {} }).into_string();
+    assert_eq!(s, r#"<div class="foo"></div>"#);
+}
+
+#[test]
+fn minify_off() {
+    let s = html!(minify = false, { div class="foo" {} }).into_string();
+    assert_eq!(s, r#"<div class="foo"></div>"#);
+}
+
+#[test]
+fn minify_with_writer() {
+    let mut s = String::new();
+    html!(&mut s, minify = true, { input type="text" name="q"; }).unwrap();
+    assert_eq!(s, "<input type=text name=q>");
+}
+
+#[test]
+fn xhtml_void_elements() {
+    let s = html!(xhtml = true, {
+        p { "Line one" br; "Line two" }
+        img src="x";
+    }).into_string();
+    assert_eq!(s, r#"<p>Line one<br />Line two</p><img src="x" />"#);
+}
+
+#[test]
+fn xhtml_empty_attributes() {
+    let checked = false;
+    let s = html!(xhtml = true, {
+        input type="checkbox" disabled? checked?[checked];
+    }).into_string();
+    assert_eq!(s, r#"<input type="checkbox" disabled="disabled" />"#);
+}
+
+#[test]
+fn sort_attrs() {
+    let s = html!(sort_attrs = true, {
+        div z="1" a="2" {}
+        input#name.wide type="text" disabled? Name="x";
+    }).into_string();
+    assert_eq!(s, concat!(
+            r#"<div a="2" z="1"></div>"#,
+            r#"<input class="wide" disabled id="name" Name="x" type="text">"#));
+}
+
+#[test]
+fn sort_attrs_defaults() {
+    let base = [("title", "Hi"), ("aria-label", "Greeting"), ("class", "base")];
+    let s = html!(sort_attrs = true, {
+        span z="1" @defaults(base.iter().cloned()) class="own" {}
+    }).into_string();
+    assert_eq!(s, r#"<span aria-label="Greeting" class="own" title="Hi" z="1"></span>"#);
+
+    let hidden = true;
+    let s = html!(sort_attrs = true, {
+        input hidden?[hidden] @defaults([("type", "text")]) @defaults([("autofocus", "")]) name=("q");
+    }).into_string();
+    assert_eq!(s, r#"<input autofocus="" hidden name="q" type="text">"#);
+}
+
+#[test]
+fn nonce() {
+    let nonce = String::from("r4nd\"0m");
+    let s = html!(nonce = nonce, {
+        script src="/app.js" {}
+        style { "p { color: red }" }
+        script nonce="other" {}
+        p { "No nonce here" }
+    }).into_string();
+    assert_eq!(s, concat!(
+            r#"<script src="/app.js" nonce="r4nd&quot;0m"></script>"#,
+            r#"<style nonce="r4nd&quot;0m">p { color: red }</style>"#,
+            r#"<script nonce="other"></script>"#,
+            "<p>No nonce here</p>"));
+}
+
+#[test]
+fn nonce_with_writer() {
+    fn page(w: &mut dyn fmt::Write, nonce: &str) -> fmt::Result {
+        html!(w, nonce = nonce, { script { "init()" } })
+    }
+
+    let mut s = String::new();
+    page(&mut s, "abc123").unwrap();
+    assert_eq!(s, r#"<script nonce="abc123">init()</script>"#);
+}
@@ -0,0 +1,82 @@
+use maud::{html, render_pooled, Markup, PooledHtml};
+
+fn page(title: &str) -> Markup {
+    html! {
+        html {
+            head { title { (title) } }
+            body { h1 { (title) } p { "Lots of <content> here." } }
+        }
+    }
+}
+
+#[test]
+fn pooled_matches_unpooled() {
+    let pooled = render_pooled(&page("Pinkie & Pie"), 0);
+    assert_eq!(&*pooled, page("Pinkie & Pie").into_string());
+    assert_eq!(pooled.into_string(), page("Pinkie & Pie").into_string());
+}
+
+#[test]
+fn pooled_writer() {
+    let mut pooled = PooledHtml::with_capacity(64);
+    html!(&mut pooled, { p { "Hello, " ("<world>") } }).unwrap();
+    assert_eq!(&*pooled, "<p>Hello, &lt;world&gt;</p>");
+    // Already markup, so it isn't escaped again when spliced
+    assert_eq!(html! { div { (pooled) } }.into_string(), "<div><p>Hello, &lt;world&gt;</p></div>");
+}
+
+#[test]
+fn pooled_option() {
+    let name = "Pinkie & Pie";
+    let pooled: PooledHtml = html!(pooled = true, { p.name { (name) } });
+    assert_eq!(&*pooled, html! { p.name { (name) } }.into_string());
+    // It reserves at least enough for the static parts
+    let first = html!(pooled = true, { p { "Hello!" } });
+    assert!(first.capacity() >= "<p>Hello!</p>".len());
+    let address = first.as_ptr();
+    drop(first);
+    assert_eq!(html!(pooled = true, { br; }).as_ptr(), address);
+    assert_eq!(maud::html_string!(pooled = true, { p { (name) } }), "<p>Pinkie &amp; Pie</p>");
+}
+
+#[test]
+fn splices_use_their_own_pool() {
+    let page = PooledHtml::with_capacity(10_000);
+    let address = page.as_ptr();
+    drop(page);
+    let mut s = String::new();
+    html!(&mut s, { p { ("a splice") } }).unwrap();
+    assert_eq!(s, "<p>a splice</p>");
+    assert_eq!(PooledHtml::with_capacity(0).as_ptr(), address);
+}
+
+#[test]
+fn buffers_are_reused() {
+    let first = PooledHtml::with_capacity(10_000);
+    let address = first.as_ptr();
+    drop(first);
+    let second = render_pooled(&page("Rarity"), 100);
+    assert_eq!(second.as_ptr(), address);
+    assert!(second.len() < 10_000);
+}
+
+#[test]
+fn best_fit() {
+    let small = PooledHtml::with_capacity(100);
+    let large = PooledHtml::with_capacity(100_000);
+    let (small_address, large_address) = (small.as_ptr(), large.as_ptr());
+    drop(small);
+    drop(large);
+    let fits = PooledHtml::with_capacity(50_000);
+    assert_eq!(fits.as_ptr(), large_address);
+    let tight = PooledHtml::with_capacity(50);
+    assert_eq!(tight.as_ptr(), small_address);
+}
+
+#[test]
+fn huge_buffers_are_not_kept() {
+    drop(PooledHtml::with_capacity(16 << 20));
+    assert_eq!(PooledHtml::with_capacity(0).capacity(), 0);
+    drop(PooledHtml::with_capacity(1000));
+    assert!(PooledHtml::with_capacity(0).capacity() >= 1000);
+}
@@ -0,0 +1,106 @@
+use maud::{html, PreEscaped};
+
+fn indented(markup: &maud::Markup, base_indent: usize, indent_step: usize) -> String {
+    let mut s = String::new();
+    markup.render_indented(&mut s, base_indent, indent_step).unwrap();
+    s
+}
+
+#[test]
+fn base_indent() {
+    let markup = html! {
+        nav {
+            ul {
+                li { a href="/" { "Home" } }
+                li.active { "About" }
+            }
+        }
+    };
+    assert_eq!(indented(&markup, 2, 4), concat!(
+        "  <nav>\n",
+        "      <ul>\n",
+        "          <li><a href=\"/\">Home</a></li>\n",
+        "          <li class=\"active\">About</li>\n",
+        "      </ul>\n",
+        "  </nav>\n",
+    ));
+}
+
+#[test]
+fn zero_indent() {
+    let markup = html! { p { "Hello, " b { "world" } "!" } };
+    assert_eq!(indented(&markup, 0, 0), "<p>Hello, <b>world</b>!</p>\n");
+}
+
+#[test]
+fn void_elements_and_doctype() {
+    let markup = html! {
+        (maud::DOCTYPE)
+        html {
+            head { meta charset="utf-8"; }
+            body { br; input type="text" /; }
+        }
+    };
+    assert_eq!(indented(&markup, 0, 1), concat!(
+        "<!DOCTYPE html>\n",
+        "<html>\n",
+        " <head>\n",
+        "  <meta charset=\"utf-8\">\n",
+        " </head>\n",
+        " <body><br><input type=\"text\"></body>\n",
+        "</html>\n",
+    ));
+}
+
+#[test]
+fn preformatted_untouched() {
+    let markup = html! {
+        div {
+            pre { "  fn main() {\n      <b>\n  }" }
+            script { (PreEscaped("if (a < b) { run(\"</p>\"); }")) }
+        }
+    };
+    assert_eq!(indented(&markup, 1, 2), concat!(
+        " <div>\n",
+        "   <pre>  fn main() {\n      &lt;b&gt;\n  }</pre>\n",
+        "   <script>if (a < b) { run(\"</p>\"); }</script>\n",
+        " </div>\n",
+    ));
+}
+
+#[test]
+fn quoted_angle_brackets() {
+    let markup = PreEscaped("<p title='a > b'><!-- <em> -->Hi</p>");
+    assert_eq!(indented(&html!((markup)), 0, 2), concat!(
+        "<p title='a > b'>\n",
+        "  <!-- <em> -->\n",
+        "  Hi\n",
+        "</p>\n",
+    ));
+}
+
+#[test]
+fn inline_and_block_children() {
+    let markup = html! {
+        article {
+            h1 { "Ponies " small { "(all of them)" } }
+            "Ranked by " em { "cuteness" } ":"
+            ol {
+                li { a href="/fluttershy" { "Fluttershy" } }
+            }
+            p { "See " a href="/more" { "more" } "." }
+            span { "Last updated" }
+        }
+    };
+    assert_eq!(indented(&markup, 0, 2), concat!(
+        "<article>\n",
+        "  <h1>Ponies <small>(all of them)</small></h1>\n",
+        "  Ranked by <em>cuteness</em>:\n",
+        "  <ol>\n",
+        "    <li><a href=\"/fluttershy\">Fluttershy</a></li>\n",
+        "  </ol>\n",
+        "  <p>See <a href=\"/more\">more</a>.</p>\n",
+        "  <span>Last updated</span>\n",
+        "</article>\n",
+    ));
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "hot-reload")]
+
+use maud::{html, html_include, Render};
+use maud::reload::{render_file, try_render_file};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_template(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("maud-reload-{}-{}.maud", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn include_matches_compiled() {
+    let size = "big";
+    let id = "pinkie";
+    let name = "<Pinkie Pie>";
+    let reloaded = html_include!("templates/card.maud").into_string();
+    let compiled = html! {
+        div.card.(size)#(id) data-kind="pony" {
+            h2 title=(name) "@click"="open = !open" { (name) }
+            input type="checkbox" checked?;
+            { "Likes: " "cupcakes & " r#"parties"# }
+        }
+    }.into_string();
+    assert_eq!(reloaded, compiled);
+}
+
+#[test]
+fn include_falls_back_to_compiled() {
+    let items = ["one", "two"];
+    let result = html_include!("templates/list.maud");
+    assert_eq!(result.into_string(), "<ul><li>one</li><li>two</li></ul>");
+}
+
+#[test]
+fn include_with_partial_falls_back_to_compiled() {
+    let year = 2011;
+    let result = html_include!("templates/partials/page.maud");
+    assert_eq!(result.into_string(), r#"<div class="page"><footer>Since 2011</footer></div>"#);
+}
+
+#[test]
+fn reloads_on_change() {
+    let path = temp_template("change", r#"p { "Hello, " (name) }"#);
+    let name = "Rarity";
+    let vars: &[(&str, &dyn Render)] = &[("name", &name)];
+    assert_eq!(render_file(&path, vars).into_string(), "<p>Hello, Rarity</p>");
+    fs::write(&path, r#"p.greeting { "Goodbye, " (name) "!" }"#).unwrap();
+    assert_eq!(
+        render_file(&path, vars).into_string(),
+        r#"<p class="greeting">Goodbye, Rarity!</p>"#,
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unknown_variable() {
+    let path = temp_template("unknown", r#"p { (name) }"#);
+    let error = try_render_file(&path, &[]).unwrap_err();
+    assert!(error.to_string().contains("unknown variable `name`"));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn syntax_error() {
+    let path = temp_template("syntax", "p {\n    @if true { \"yes\" }\n}\n");
+    let error = try_render_file(&path, &[]).unwrap_err();
+    assert!(error.to_string().contains(":2: `@` keywords aren't supported"), "{}", error);
+    let rendered = render_file(&path, &[]).into_string();
+    assert!(rendered.starts_with("<pre>"));
+    fs::remove_file(&path).unwrap();
+}
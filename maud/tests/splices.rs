@@ -1,5 +1,3 @@
-#![feature(proc_macro_hygiene)]
-
 use maud::html;
 
 #[test]
@@ -29,6 +27,20 @@ fn blocks() {
     assert_eq!(s, "3628800");
 }
 
+#[test]
+fn block_expressions() {
+    fn compute() -> i32 { 21 }
+    let s = html!(({ let x = compute(); x * 2 })).into_string();
+    assert_eq!(s, "42");
+}
+
+#[test]
+fn binary_operators() {
+    let (a, b) = (40, 2);
+    let s = html!((a + b) " " (a - b * 2) " " (b as f64 / 4.0)).into_string();
+    assert_eq!(s, "42 36 0.5");
+}
+
 #[test]
 fn attributes() {
     let alt = "Pinkie Pie";
@@ -36,6 +48,18 @@ fn attributes() {
     assert_eq!(s, r#"<img src="pinkie.jpg" alt="Pinkie Pie">"#);
 }
 
+#[test]
+fn pre_escaped_attributes() {
+    let json = maud::PreEscaped(r#"{&quot;pony&quot;:&quot;Rarity&quot;}"#);
+    let raw = r#"{"pony":"Rarity"}"#;
+    let s = html! {
+        div data-json=(json) data-raw=(raw) {}
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<div data-json="{&quot;pony&quot;:&quot;Rarity&quot;}" "#,
+            r#"data-raw="{&quot;pony&quot;:&quot;Rarity&quot;}"></div>"#));
+}
+
 #[test]
 fn class_shorthand() {
     let pinkie_class = "pinkie";
@@ -50,6 +74,30 @@ fn class_shorthand_block() {
     assert_eq!(s, r#"<p class="pinkie-123">Fun!</p>"#);
 }
 
+#[test]
+fn class_shorthand_mixed() {
+    fn test(size: &str, is_active: bool) -> String {
+        html!(div.col.(size)[!size.is_empty()].active[is_active].(format!("col-{}", 2)) {}).into_string()
+    }
+    assert_eq!(test("large", true), r#"<div class="col large active col-2"></div>"#);
+    assert_eq!(test("", true), r#"<div class="col active col-2"></div>"#);
+    assert_eq!(test("large", false), r#"<div class="col large col-2"></div>"#);
+}
+
+#[test]
+fn class_shorthand_empty() {
+    let empty = "";
+    let s = html!(p.(empty).pinkie.(empty).pie.(empty) { "Fun!" }).into_string();
+    assert_eq!(s, r#"<p class="pinkie pie">Fun!</p>"#);
+}
+
+#[test]
+fn class_shorthand_escaped() {
+    let pinkie_class = "<pinkie>";
+    let s = html!(p.party.(pinkie_class).{ "pie-" (1) } { "Fun!" }).into_string();
+    assert_eq!(s, r#"<p class="party &lt;pinkie&gt; pie-1">Fun!</p>"#);
+}
+
 #[test]
 fn id_shorthand() {
     let pinkie_id = "pinkie";
@@ -57,7 +105,7 @@ fn id_shorthand() {
     assert_eq!(s, r#"<p id="pinkie">Fun!</p>"#);
 }
 
-static BEST_PONY: &'static str = "Pinkie Pie";
+static BEST_PONY: &str = "Pinkie Pie";
 
 #[test]
 fn statics() {
@@ -123,3 +171,291 @@ fn nested_macro_invocation() {
     let s = html!((format!("{} is best pony", best_pony))).into_string();
     assert_eq!(s, "Pinkie Pie is best pony");
 }
+
+#[test]
+fn nested_html_inlined() {
+    let name = "<Rarity>";
+    let s = html! {
+        div {
+            (html! { p { "Hello, " (name) } })
+            (maud::html!(@let shout = name.to_uppercase(); b { (shout) }))
+            (::maud::html! { i { "!" } })
+        }
+    }.into_string();
+    assert_eq!(s, "<div><p>Hello, &lt;Rarity&gt;</p><b>&lt;RARITY&gt;</b><i>!</i></div>");
+
+    // Inlined static text is merged with the rest, so the size is known
+    let s = html! { p { (maud::html! { "Fluttershy" }) } }.into_string();
+    assert_eq!(s, "<p>Fluttershy</p>");
+    assert!(s.capacity() >= s.len());
+}
+
+#[test]
+fn nested_html_with_options() {
+    let s = html! {
+        div { (html!(minify = true, { p class="a" { "Not inlined" } })) }
+    }.into_string();
+    assert_eq!(s, "<div><p class=a>Not inlined</p></div>");
+}
+
+#[test]
+fn nested_html_keeps_default_options() {
+    // The outer options don't carry over into the nested template
+    let s = html!(minify = true, xhtml = true, {
+        div { (maud::html! { p class="a" { br; } }) }
+    }).into_string();
+    assert_eq!(s, "<div><p class=\"a\"><br></p></div>");
+}
+
+mod shadowed {
+    use maud::PreEscaped;
+
+    macro_rules! html {
+        ($($tokens:tt)*) => { PreEscaped("<i>not maud</i>") };
+    }
+    // So that it can be called as `self::html!`
+    use html;
+
+    #[test]
+    fn nested_html_by_path() {
+        // A bare `html!` might be another macro, so it's left alone
+        let s = maud::html! { (html! { p { "maud" } }) }.into_string();
+        assert_eq!(s, "<i>not maud</i>");
+        let s = maud::html! { (self::html! { p { "maud" } }) }.into_string();
+        assert_eq!(s, "<i>not maud</i>");
+        let s = maud::html! { (maud::html! { p { "maud" } }) }.into_string();
+        assert_eq!(s, "<p>maud</p>");
+    }
+}
+
+#[test]
+fn format_spec() {
+    let price = 19.99512;
+    let s = html! {
+        (price:.2) " "
+        td { (price:>8.3) }
+        code { (255:#x) " " (7:03) " " ("<b>":^7) }
+    }.into_string();
+    assert_eq!(s, "20.00 <td>  19.995</td><code>0xff 007   &lt;b&gt;  </code>");
+}
+
+#[test]
+fn text_splice_escapes() {
+    let comment = "<script>alert('hi')</script> & more";
+    let s = html! {
+        p title=@text (comment) { @text (comment) }
+        span { @text (3 + 4) }
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<p title="&lt;script&gt;alert('hi')&lt;/script&gt; &amp; more">"#,
+            "&lt;script&gt;alert('hi')&lt;/script&gt; &amp; more</p>",
+            "<span>7</span>"));
+}
+
+#[test]
+fn truncate_counts_chars() {
+    let review = "Très bon café — à recommander !";
+    let s = html! {
+        p { @truncate 10 (review) }
+        p { @truncate 31 (review) }
+        p title=@truncate 4 ("<b>&") { @truncate 2 ("<b>&") }
+    }.into_string();
+    assert_eq!(s, concat!(
+            "<p>Très bon c…</p>",
+            "<p>Très bon café — à recommander !</p>",
+            r#"<p title="&lt;b&gt;&amp;">&lt;b…</p>"#));
+    let preview = html! { @truncate 10 (review) }.into_string();
+    assert_eq!(preview.chars().count(), 11);
+    assert!(preview.ends_with('…'));
+}
+
+#[test]
+fn srcset_from_pairs() {
+    let descriptors: &[(&str, &str)] = &[("/pony.png", "1x"), ("/pony@2x.png?v=1&q=\"hi\"", "2x")];
+    let s = html!(img src="/pony.png" srcset=(descriptors) alt="Pony";).into_string();
+    assert_eq!(s, concat!(
+            r#"<img src="/pony.png" "#,
+            r#"srcset="/pony.png 1x, /pony@2x.png?v=1&amp;q=&quot;hi&quot; 2x" alt="Pony">"#));
+}
+
+#[test]
+fn srcset_from_string_and_vec() {
+    let widths = vec![("small.jpg".to_string(), format!("{}w", 480)), ("fallback.jpg".to_string(), String::new())];
+    let plain = "a.jpg 1x";
+    let s = html! {
+        img srcset=(widths);
+        img srcset=(plain);
+    }.into_string();
+    assert_eq!(s, r#"<img srcset="small.jpg 480w, fallback.jpg"><img srcset="a.jpg 1x">"#);
+}
+
+#[test]
+fn srcset_from_display() {
+    struct Url(&'static str);
+    impl std::fmt::Display for Url {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{} 2x", self.0)
+        }
+    }
+    let url = Url("/pony.png?a&b");
+    let markup = html! { "/pony.png 1x" };
+    let s = html! {
+        img srcset=(url);
+        img srcset=(&url);
+        img srcset=(markup);
+        img srcset=(2);
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<img srcset="/pony.png?a&amp;b 2x"><img srcset="/pony.png?a&amp;b 2x">"#,
+            r#"<img srcset="/pony.png 1x"><img srcset="2">"#));
+}
+
+#[test]
+fn format_args_in_attribute() {
+    use std::fmt;
+
+    struct Color(u8, u8, u8);
+    impl fmt::Display for Color {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+        }
+    }
+
+    let color = Color(255, 0, 170);
+    let font = r#""Comic Sans" <MS>"#;
+    let s = html! {
+        div style=(format_args!("color:{}", color)) {}
+        p style={ "font-family:" (format_args!("{}, sans-serif", font)) } {}
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<div style="color:#ff00aa"></div>"#,
+            r#"<p style="font-family:&quot;Comic Sans&quot; &lt;MS&gt;, sans-serif"></p>"#));
+}
+
+#[test]
+fn format_spec_in_attribute() {
+    let width = 12.5;
+    let s = html!(col width=(width:.0);).into_string();
+    assert_eq!(s, r#"<col width="12">"#);
+}
+
+#[test]
+fn format_alternate() {
+    use std::fmt;
+
+    struct Size(u64);
+    impl fmt::Display for Size {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if f.alternate() {
+                write!(f, "{} bytes <exact>", self.0)
+            } else {
+                write!(f, "{}K", self.0 / 1024)
+            }
+        }
+    }
+
+    let size = Size(2048);
+    let s = html! {
+        (size) " " (size#) " " (size:#)
+        data value=(size#) {}
+    }.into_string();
+    assert_eq!(s, concat!(
+            "2K 2048 bytes &lt;exact&gt; 2048 bytes &lt;exact&gt;",
+            r#"<data value="2048 bytes &lt;exact&gt;"></data>"#));
+}
+
+#[test]
+fn colons_without_format_spec() {
+    let parsed = ["1", "x", "3"].iter().filter_map(|s: &&str| s.parse::<u32>().ok()).count();
+    let s = html!((parsed) " " ([1, 2].iter().map(|x: &u32| x * 2).sum::<u32>()) " " (std::f64::consts::E:.1)).into_string();
+    assert_eq!(s, "2 6 2.7");
+}
+
+#[test]
+fn env_variables() {
+    let s = html!(footer { "v" @env "CARGO_PKG_VERSION" " by " @env "CARGO_PKG_AUTHORS" }).into_string();
+    // The values are escaped like any other text
+    let mut authors = String::new();
+    std::fmt::Write::write_str(&mut maud::Escaper::new(&mut authors), env!("CARGO_PKG_AUTHORS")).unwrap();
+    assert_eq!(s, format!("<footer>v{} by {}</footer>", env!("CARGO_PKG_VERSION"), authors));
+}
+
+#[test]
+fn lazy_values() {
+    use std::cell::{Cell, LazyCell};
+    use std::rc::Rc;
+    use std::sync::LazyLock;
+
+    static VERSION: LazyLock<String> = LazyLock::new(|| format!("v{}", 1 + 1));
+    let computed = Cell::new(false);
+    let sidebar = LazyCell::new(|| {
+        computed.set(true);
+        html!(aside { "<expensive>" })
+    });
+    let shared = Rc::new(Box::new("pony"));
+    let render = |show_sidebar: bool| html! {
+        (VERSION) " " (shared)
+        @if show_sidebar { (sidebar) }
+    }.into_string();
+    assert_eq!(render(false), "v2 pony");
+    assert!(!computed.get());
+    assert_eq!(render(true), "v2 pony<aside>&lt;expensive&gt;</aside>");
+    assert!(computed.get());
+}
+
+#[test]
+fn splice_else() {
+    let render = |nickname: Option<&str>, age: Option<u32>| html! {
+        p title=(nickname else "none") {
+            (nickname else em { "N/A" }) ", " (age else "?")
+        }
+    }.into_string();
+    assert_eq!(render(Some("<Pinkie>"), Some(12)), r#"<p title="&lt;Pinkie&gt;">&lt;Pinkie&gt;, 12</p>"#);
+    assert_eq!(render(None, None), r#"<p title="none"><em>N/A</em>, ?</p>"#);
+}
+
+#[test]
+fn splice_catch_panic() {
+    use std::fmt;
+
+    struct Flaky(bool);
+    impl fmt::Display for Flaky {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("partial <output>")?;
+            if self.0 {
+                panic!("flaky display");
+            }
+            Ok(())
+        }
+    }
+
+    let render = |flaky: Flaky| html! {
+        p { (flaky ?? "error") }
+        p { (Flaky(true) ?? em { "failed to render" }) }
+    }.into_string();
+    assert_eq!(render(Flaky(false)), "<p>partial &lt;output&gt;</p><p><em>failed to render</em></p>");
+    assert_eq!(render(Flaky(true)), "<p>error</p><p><em>failed to render</em></p>");
+
+    let mut s = String::new();
+    html!(&mut s, { b { (Flaky(true) ?? "x") } }).unwrap();
+    assert_eq!(s, "<b>x</b>");
+}
+
+#[test]
+fn splice_double_question_mark() {
+    fn nested(value: Option<Option<u32>>) -> Option<String> {
+        Some(html!((value??)).into_string())
+    }
+    assert_eq!(nested(Some(Some(3))), Some("3".to_string()));
+    assert_eq!(nested(Some(None)), None);
+}
+
+#[test]
+fn splice_else_borrowed() {
+    let names = vec![Some("Twilight".to_string()), None];
+    let s = html! {
+        @for name in &names { (name else "Anonymous") " " }
+        (if names.is_empty() { "empty" } else { "not empty" })
+    }.into_string();
+    assert_eq!(s, "Twilight Anonymous not empty");
+}
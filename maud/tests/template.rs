@@ -0,0 +1,81 @@
+use maud::{html, template};
+use std::fmt::Display;
+
+struct User {
+    name: &'static str,
+    admin: bool,
+}
+
+template! {
+    /// Renders a summary of a user.
+    pub fn user_card(user: &User, compact: bool) {
+        div.card {
+            (user.name)
+            @if user.admin && !compact {
+                " (admin)"
+            }
+        }
+    }
+}
+
+template! {
+    fn list<'a, T>(items: &'a [T]) where T: Display {
+        ul {
+            @for item in items {
+                li { (item) }
+            }
+        }
+    }
+}
+
+template! {
+    fn counted<F: Fn() -> u8, G>(count: F, label: G) where G: Fn(u8) -> String {
+        p { (label(count())) }
+    }
+}
+
+mod cards {
+    use maud::template;
+
+    template! {
+        pub(crate) fn badge(text: &str) {
+            span.badge { (text) }
+        }
+    }
+}
+
+#[test]
+fn template_function() {
+    let user = User { name: "Celestia", admin: true };
+    assert_eq!(user_card(&user, false).into_string(), r#"<div class="card">Celestia (admin)</div>"#);
+    assert_eq!(user_card(&user, true).into_string(), r#"<div class="card">Celestia</div>"#);
+}
+
+#[test]
+fn template_generics() {
+    assert_eq!(list(&[1, 2]).into_string(), "<ul><li>1</li><li>2</li></ul>");
+}
+
+#[test]
+fn template_fn_bounds() {
+    let s = counted(|| 3, |n| format!("{} ponies", n)).into_string();
+    assert_eq!(s, "<p>3 ponies</p>");
+}
+
+#[test]
+fn template_in_splice() {
+    let users = [
+        User { name: "Luna", admin: false },
+        User { name: "<Discord>", admin: false },
+    ];
+    let s = html! {
+        @for user in &users {
+            (user_card(user, true))
+        }
+        (cards::badge("new"))
+    }.into_string();
+    assert_eq!(s, concat!(
+            r#"<div class="card">Luna</div>"#,
+            r#"<div class="card">&lt;Discord&gt;</div>"#,
+            r#"<span class="badge">new</span>"#));
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "trace")]
+
+use maud::html;
+use maud::trace::{self, Splice};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn trace_splices() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let collect = seen.clone();
+    trace::set_callback(move |splice: &Splice| {
+        collect.lock().unwrap().push((splice.file, splice.line, splice.value.to_string()));
+    });
+
+    let name = "Pinkie <Pie>";
+    let count = 3;
+    let first_line = line!() + 3;
+    let render = || {
+        html! {
+            p { (name) }
+            p title=(count) { "Cupcakes" }
+        }
+    };
+
+    render();
+    assert!(seen.lock().unwrap().is_empty());
+
+    trace::set_trace(true);
+    let s = render().into_string();
+    let writer_line = line!() + 3;
+    let writer = {
+        let mut s = String::new();
+        html!(&mut s, { span { (count * 2) } }).unwrap();
+        s
+    };
+    trace::set_trace(false);
+    render();
+
+    assert_eq!(s, r#"<p>Pinkie &lt;Pie&gt;</p><p title="3">Cupcakes</p>"#);
+    assert_eq!(writer, "<span>6</span>");
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 3);
+    assert!(seen.iter().all(|&(file, _, _)| file.ends_with("trace.rs")));
+    assert_eq!((seen[0].1, seen[0].2.as_str()), (first_line, "Pinkie &lt;Pie&gt;"));
+    assert_eq!((seen[1].1, seen[1].2.as_str()), (first_line + 1, "3"));
+    assert_eq!((seen[2].1, seen[2].2.as_str()), (writer_line, "6"));
+}
@@ -1,15 +1,51 @@
-use compiletest_rs;
-
+use std::fs;
 use std::path::PathBuf;
 
 #[test]
 fn run_warnings() {
-    let mut config = compiletest_rs::Config::default();
+    run_ui_tests("tests/warnings");
+}
+
+#[test]
+fn run_errors() {
+    run_ui_tests("tests/errors");
+}
+
+fn run_ui_tests(src_base: &str) {
+    let mut config = compiletest_rs::Config {
+        mode: compiletest_rs::common::Mode::Ui,
+        src_base: PathBuf::from(src_base),
+        ..Default::default()
+    };
 
-    config.mode = compiletest_rs::common::Mode::Ui;
-    config.src_base = PathBuf::from("tests/warnings");
     config.link_deps(); // Populate config.target_rustcflags with dependencies on the path
     config.clean_rmeta(); // If your tests import the parent crate, this helps with E0464
+    pin_extern(&mut config, "maud");
+    pin_extern(&mut config, "maud_macros");
 
     compiletest_rs::run_tests(&config);
 }
+
+/// Points rustc at the most recently built copy of a dependency.
+///
+/// `cargo build` and `cargo test` can resolve features differently, which
+/// leaves several copies of the same crate in `deps` and causes E0464.
+fn pin_extern(config: &mut compiletest_rs::Config, krate: &str) {
+    let flags = config.target_rustcflags.take().unwrap_or_default();
+    let prefix = format!("lib{}-", krate);
+    let newest = flags
+        .split_whitespace()
+        .filter(|flag| flag.ends_with("/deps"))
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with(&prefix) && !name.ends_with(".d")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max();
+    config.target_rustcflags = Some(match newest {
+        Some((_, path)) => format!("{} --extern {}={}", flags, krate, path.display()),
+        None => flags,
+    });
+}
@@ -0,0 +1,31 @@
+#![feature(proc_macro_hygiene)]
+
+extern crate maud_macros;
+
+use maud_macros::html;
+
+fn main() {
+    let description = "A pony";
+    let has_alt = true;
+    html!(warn(accessibility), {
+        html {
+            img src="pony.png";
+            img src="pony.png" alt=(description);
+            img src="pony.png" alt="";
+            img src="pony.png" alt?[has_alt];
+            a { "Nowhere" }
+            a href="/" { "Home" }
+            label for="name" { "Name" }
+            input#name type="text";
+            input#email type="email";
+            input#token type="hidden";
+            input type="submit";
+        }
+    });
+    html!(warn(accessibility), allow(missing_alt), {
+        img src="pony.png";
+    });
+    html! {
+        img src="Not linted";
+    };
+}
@@ -0,0 +1,18 @@
+#![feature(proc_macro_hygiene)]
+
+extern crate maud_macros;
+
+use maud_macros::html;
+
+fn main() {
+    let url = "javascript:alert(1)";
+    html!(warn(security), {
+        button onclick="launch()" { "Launch" }
+        a href="javascript:void(0)" { "Nowhere" }
+        a href=(url) { "Not checked" }
+        img src="pony.png";
+    });
+    html! {
+        button onclick="launch()" { "Not linted" }
+    };
+}
@@ -0,0 +1,17 @@
+extern crate maud;
+
+use maud::html;
+
+fn main() {
+    let url = "data:text/html,<script>alert(1)</script>";
+    html!(warn(unsafe_urls), {
+        a href="javascript:alert(1)" { "Warned" }
+        a href="data:text/html;base64,PHNjcmlwdD4=" { "Also warned" }
+        iframe src="data:image/svg+xml,<svg/>" {}
+        img src="data:image/png;base64,iVBORw0KGgo=" alt="Fine";
+        a href=(url) { "Not checked" }
+    });
+    html! {
+        a href="javascript:void(0)" { "Off by default" }
+    };
+}
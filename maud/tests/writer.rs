@@ -0,0 +1,152 @@
+use maud::html;
+use std::fmt::{self, Write};
+
+#[test]
+fn write_to_string() {
+    let name = "Lyra";
+    let mut s = String::new();
+    let result = html!(&mut s, {
+        p { "Hi, " (name) "!" }
+    });
+    assert_eq!(result, Ok(()));
+    assert_eq!(s, "<p>Hi, Lyra!</p>");
+}
+
+#[test]
+fn write_appends() {
+    let mut s = String::from("<!-- header -->");
+    html!(&mut s, { br; }).unwrap();
+    assert_eq!(s, "<!-- header --><br>");
+}
+
+#[test]
+fn write_escapes_splices() {
+    let mut s = String::new();
+    html!(&mut s, { ("<pinkie>") }).unwrap();
+    assert_eq!(s, "&lt;pinkie&gt;");
+}
+
+#[test]
+fn write_to_trait_object() {
+    fn greet(w: &mut dyn Write, name: &str) -> fmt::Result {
+        html!(w, { "Hello, " (name) "!" })
+    }
+
+    let mut s = String::new();
+    greet(&mut s, "Pinkie Pie").unwrap();
+    assert_eq!(s, "Hello, Pinkie Pie!");
+}
+
+#[test]
+fn write_to_formatter() {
+    struct Pony(&'static str);
+
+    impl fmt::Display for Pony {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            html!(f, { b { (self.0) } })
+        }
+    }
+
+    assert_eq!(Pony("Rarity").to_string(), "<b>Rarity</b>");
+}
+
+#[test]
+fn write_stops_on_error() {
+    /// Accepts up to `remaining` bytes, then fails.
+    struct Limited {
+        output: String,
+        remaining: usize,
+    }
+
+    impl Write for Limited {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if s.len() > self.remaining {
+                return Err(fmt::Error);
+            }
+            self.remaining -= s.len();
+            self.output.push_str(s);
+            Ok(())
+        }
+    }
+
+    let mut w = Limited { output: String::new(), remaining: 8 };
+    let result = html!(&mut w, {
+        @for i in 0..10 {
+            p { (i) }
+        }
+    });
+    assert_eq!(result, Err(fmt::Error));
+    assert_eq!(w.output, "<p>0</p>");
+}
+
+#[test]
+fn write_recursively() {
+    struct Node {
+        name: &'static str,
+        children: Vec<Node>,
+    }
+
+    fn tree(w: &mut dyn Write, node: &Node) -> fmt::Result {
+        html!(w, {
+            li {
+                (node.name)
+                @if !node.children.is_empty() {
+                    ul {
+                        @for child in &node.children {
+                            @let () = tree(w, child)?;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    let root = Node {
+        name: "Equestria",
+        children: vec![
+            Node { name: "Ponyville", children: vec![] },
+            Node {
+                name: "Canterlot",
+                children: vec![Node { name: "Castle", children: vec![] }],
+            },
+        ],
+    };
+    let mut s = String::new();
+    tree(&mut s, &root).unwrap();
+    assert_eq!(s, concat!(
+            "<li>Equestria<ul>",
+            "<li>Ponyville</li>",
+            "<li>Canterlot<ul><li>Castle</li></ul></li>",
+            "</ul></li>"));
+}
+
+#[test]
+fn render_into_buf() {
+    let name = "Trixie";
+    let mut buf = [0; 32];
+    let len = maud::render_into_buf(|w| html!(w, { p { "The Great and Powerful " (name) } }), &mut buf);
+    assert_eq!(len, Err(maud::Overflow));
+
+    let mut buf = [0; 64];
+    let len = maud::render_into_buf(|w| html!(w, { p { "The Great and Powerful " (name) } }), &mut buf).unwrap();
+    assert_eq!(&buf[..len], &b"<p>The Great and Powerful Trixie</p>"[..]);
+}
+
+#[test]
+fn markup_into_buf() {
+    let markup = html! { b { "Hi" } };
+    let mut buf = [0; 9];
+    assert_eq!(markup.render_into_buf(&mut buf), Ok(9));
+    assert_eq!(&buf, b"<b>Hi</b>");
+    assert_eq!(markup.render_into_buf(&mut buf[..8]), Err(maud::Overflow));
+}
+
+#[test]
+fn slice_writer_keeps_whole_writes() {
+    let mut buf = [0; 8];
+    let mut w = maud::SliceWriter::new(&mut buf);
+    w.write_str("<br>").unwrap();
+    assert!(w.write_str("<hr>!").is_err());
+    assert!(w.overflowed());
+    assert_eq!(w.as_str(), "<br>");
+}
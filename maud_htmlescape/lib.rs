@@ -24,8 +24,10 @@ use std::fmt;
 ///
 /// # Example
 ///
-/// ```rust,ignore
+/// ```rust
+/// use maud_htmlescape::Escaper;
 /// use std::fmt::Write;
+///
 /// let mut s = String::new();
 /// write!(Escaper::new(&mut s), "<script>launchMissiles()</script>").unwrap();
 /// assert_eq!(s, "&lt;script&gt;launchMissiles()&lt;/script&gt;");
@@ -41,23 +43,78 @@ impl<'a> Escaper<'a> {
 
 impl<'a> fmt::Write for Escaper<'a> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for b in s.bytes() {
-            match b {
-                b'&' => self.0.push_str("&amp;"),
-                b'<' => self.0.push_str("&lt;"),
-                b'>' => self.0.push_str("&gt;"),
-                b'"' => self.0.push_str("&quot;"),
-                _ => unsafe { self.0.as_mut_vec().push(b) },
-            }
+        for chunk in escape_chunks(s) {
+            self.0.push_str(chunk);
+        }
+        Ok(())
+    }
+
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        let entity = if c.is_ascii() { entity(c as u8) } else { None };
+        match entity {
+            Some(entity) => self.0.push_str(entity),
+            None => self.0.push(c),
         }
         Ok(())
     }
 }
 
+/// Escapes a string without allocating, by splitting it into chunks.
+///
+/// The chunks alternate between runs of the input that don't need
+/// escaping, and entities that replace a special character. Joined
+/// together, they give the same result as [`Escaper`](struct.Escaper.html).
+///
+/// # Example
+///
+/// ```rust
+/// use maud_htmlescape::escape_chunks;
+///
+/// let chunks = escape_chunks("a < b").collect::<Vec<_>>();
+/// assert_eq!(chunks, ["a ", "&lt;", " b"]);
+/// ```
+pub fn escape_chunks(input: &str) -> EscapeChunks<'_> {
+    EscapeChunks { rest: input }
+}
+
+/// The iterator returned by [`escape_chunks`](fn.escape_chunks.html).
+#[derive(Clone, Debug)]
+pub struct EscapeChunks<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for EscapeChunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let first = *self.rest.as_bytes().first()?;
+        if let Some(entity) = entity(first) {
+            // Special characters are all ASCII, so this is a char boundary
+            self.rest = &self.rest[1..];
+            return Some(entity);
+        }
+        let end = self.rest.bytes().position(|b| entity(b).is_some()).unwrap_or(self.rest.len());
+        let (clean, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(clean)
+    }
+}
+
+/// Returns the entity for a byte, if it needs escaping.
+fn entity(b: u8) -> Option<&'static str> {
+    match b {
+        b'&' => Some("&amp;"),
+        b'<' => Some("&lt;"),
+        b'>' => Some("&gt;"),
+        b'"' => Some("&quot;"),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::fmt::Write;
-    use crate::Escaper;
+    use crate::{Escaper, escape_chunks};
 
     #[test]
     fn it_works() {
@@ -65,4 +122,55 @@ mod test {
         write!(Escaper::new(&mut s), "<script>launchMissiles()</script>").unwrap();
         assert_eq!(s, "&lt;script&gt;launchMissiles()&lt;/script&gt;");
     }
+
+    #[test]
+    fn write_char() {
+        let mut s = String::new();
+        let mut escaper = Escaper::new(&mut s);
+        for c in "<a href=\"?x&y\">é</a>".chars() {
+            escaper.write_char(c).unwrap();
+        }
+        assert_eq!(s, "&lt;a href=&quot;?x&amp;y&quot;&gt;é&lt;/a&gt;");
+    }
+
+    #[test]
+    fn chunks() {
+        let chunks = escape_chunks("a < b").collect::<Vec<_>>();
+        assert_eq!(chunks, ["a ", "&lt;", " b"]);
+        assert_eq!(escape_chunks("").count(), 0);
+        let chunks = escape_chunks("<&\"").collect::<Vec<_>>();
+        assert_eq!(chunks, ["&lt;", "&amp;", "&quot;"]);
+        let chunks = escape_chunks("café").collect::<Vec<_>>();
+        assert_eq!(chunks, ["café"]);
+    }
+
+    /// The chunks must join up to the escaped input, for every string
+    /// over a small alphabet of clean, special, and multi-byte characters.
+    #[test]
+    fn chunks_join_to_escaped() {
+        fn escape_naive(input: &str) -> String {
+            input.chars().map(|c| match c {
+                '&' => "&amp;".to_string(),
+                '<' => "&lt;".to_string(),
+                '>' => "&gt;".to_string(),
+                '"' => "&quot;".to_string(),
+                c => c.to_string(),
+            }).collect()
+        }
+        let alphabet = ['a', '&', '<', '>', '"', '\'', 'é'];
+        let mut inputs = vec![String::new()];
+        for _ in 0..6 {
+            for input in &inputs {
+                let chunks = escape_chunks(input).collect::<Vec<_>>();
+                assert_eq!(chunks.concat(), escape_naive(input), "input: {:?}", input);
+                assert!(chunks.iter().all(|chunk| !chunk.is_empty()));
+                let mut s = String::new();
+                Escaper::new(&mut s).write_str(input).unwrap();
+                assert_eq!(s, chunks.concat());
+            }
+            inputs = inputs.iter()
+                .flat_map(|input| alphabet.iter().map(move |&c| format!("{}{}", input, c)))
+                .collect();
+        }
+    }
 }
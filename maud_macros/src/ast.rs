@@ -1,4 +1,4 @@
-use proc_macro::{Span, TokenStream, TokenTree};
+use proc_macro::{Ident, Span, TokenStream, TokenTree};
 
 #[derive(Debug)]
 pub enum Markup {
@@ -10,6 +10,12 @@ pub enum Markup {
     Symbol {
         symbol: TokenStream,
     },
+    /// An XML processing instruction, like `@pi "xml version=\"1.0\""`.
+    ProcessingInstruction {
+        at_span: Span,
+        content: String,
+        content_span: Span,
+    },
     Splice {
         expr: TokenStream,
         outer_span: Span,
@@ -23,6 +29,10 @@ pub enum Markup {
         at_span: Span,
         tokens: TokenStream,
     },
+    Control {
+        at_span: Span,
+        tokens: TokenStream,
+    },
     Special {
         segments: Vec<Special>,
     },
@@ -32,6 +42,51 @@ pub enum Markup {
         arms: Vec<MatchArm>,
         arms_span: Span,
     },
+    Cfg {
+        cfg: Cfg,
+        body: Block,
+    },
+    /// A block of Rust code that writes to the output itself, as in
+    /// `@write |w| { ... }`.
+    Write {
+        at_span: Span,
+        writer: Ident,
+        body: TokenTree,
+    },
+    /// A `@for` loop with a `@sep` block between iterations.
+    Separated {
+        at_span: Span,
+        head: TokenStream,
+        body: Block,
+        sep: Block,
+    },
+    Extends {
+        at_span: Span,
+        layout: TokenStream,
+        blocks: Vec<NamedBlock>,
+        blocks_span: Span,
+    },
+    /// A splice with a fallback for when rendering it panics, as in
+    /// `(value ?? "error")`.
+    Catch {
+        at_span: Span,
+        body: Block,
+        fallback: Block,
+    },
+    /// An Internet Explorer conditional comment, like
+    /// `@ie_comment "lt IE 9" { ... }`.
+    ConditionalComment {
+        at_span: Span,
+        condition: String,
+        body: Block,
+    },
+    /// An `@around wrapper { ... }`, which passes its body to `wrapper`
+    /// as `Markup`.
+    Around {
+        at_span: Span,
+        wrapper: TokenStream,
+        body: Block,
+    },
 }
 
 impl Markup {
@@ -40,12 +95,15 @@ impl Markup {
             Markup::Block(ref block) => block.span(),
             Markup::Literal { span, .. } => span,
             Markup::Symbol { ref symbol } => span_tokens(symbol.clone()),
+            Markup::ProcessingInstruction { at_span, content_span, .. } => {
+                at_span.join(content_span).unwrap_or(at_span)
+            },
             Markup::Splice { outer_span, .. } => outer_span,
             Markup::Element { ref name, ref body, .. } => {
                 let name_span = span_tokens(name.clone());
                 name_span.join(body.span()).unwrap_or(name_span)
             },
-            Markup::Let { at_span, ref tokens } => {
+            Markup::Let { at_span, ref tokens } | Markup::Control { at_span, ref tokens } => {
                 at_span.join(span_tokens(tokens.clone())).unwrap_or(at_span)
             },
             Markup::Special { ref segments } => {
@@ -54,6 +112,37 @@ impl Markup {
             Markup::Match { at_span, arms_span, .. } => {
                 at_span.join(arms_span).unwrap_or(at_span)
             },
+            Markup::Write { at_span, ref body, .. } => at_span.join(body.span()).unwrap_or(at_span),
+            Markup::Cfg { ref cfg, ref body } => {
+                cfg.at_span.join(body.span()).unwrap_or(cfg.at_span)
+            },
+            Markup::Separated { at_span, ref sep, .. } => {
+                at_span.join(sep.span()).unwrap_or(at_span)
+            },
+            Markup::Extends { at_span, blocks_span, .. } => {
+                at_span.join(blocks_span).unwrap_or(at_span)
+            },
+            Markup::Catch { at_span, .. } => at_span,
+            Markup::ConditionalComment { at_span, ref body, .. } | Markup::Around { at_span, ref body, .. } => {
+                at_span.join(body.span()).unwrap_or(at_span)
+            },
+        }
+    }
+
+    /// Returns the text of this markup if it's known at compile time.
+    pub fn static_value(&self) -> Option<String> {
+        match *self {
+            Markup::Literal { ref content, .. } => Some(content.clone()),
+            Markup::Symbol { ref symbol } => {
+                Some(symbol.clone().into_iter().map(|token| token.to_string()).collect())
+            },
+            Markup::Block(ref block) => {
+                block.markups.iter()
+                    .map(Markup::static_value)
+                    .collect::<Option<Vec<_>>>()
+                    .map(|values| values.concat())
+            },
+            _ => None,
         }
     }
 }
@@ -74,6 +163,13 @@ pub enum Attr {
     Attribute {
         attribute: Attribute,
     },
+    /// `@defaults(pairs)`, attributes that are only written if the element
+    /// doesn't set them itself.
+    Defaults {
+        at_span: Span,
+        pairs: TokenStream,
+        pairs_span: Span,
+    },
 }
 
 impl Attr {
@@ -93,6 +189,7 @@ impl Attr {
                 hash_span.join(name_span).unwrap_or(hash_span)
             },
             Attr::Attribute { ref attribute } => attribute.span(),
+            Attr::Defaults { at_span, pairs_span, .. } => at_span.join(pairs_span).unwrap_or(at_span),
         }
     }
 }
@@ -142,10 +239,11 @@ impl Special {
 pub struct Attribute {
     pub name: TokenStream,
     pub attr_type: AttrType,
+    pub cfg: Option<Cfg>,
 }
 
 impl Attribute {
-    fn span(&self) -> Span {
+    pub fn span(&self) -> Span {
         let name_span = span_tokens(self.name.clone());
         if let Some(attr_type_span) = self.attr_type.span() {
             name_span.join(attr_type_span).unwrap_or(name_span)
@@ -163,6 +261,15 @@ pub enum AttrType {
     Empty {
         toggler: Option<Toggler>,
     },
+    /// `class=toggle[flags]`, which picks classes at runtime.
+    ClassFlags {
+        flags: TokenStream,
+        span: Span,
+    },
+    /// Class shorthand that can't be joined at compile time.
+    Classes {
+        classes: Vec<(Markup, Option<Toggler>)>,
+    },
 }
 
 impl AttrType {
@@ -170,6 +277,8 @@ impl AttrType {
         match *self {
             AttrType::Normal { ref value } => Some(value.span()),
             AttrType::Empty { ref toggler } => toggler.as_ref().map(Toggler::span),
+            AttrType::ClassFlags { span, .. } => Some(span),
+            AttrType::Classes { .. } => None,
         }
     }
 }
@@ -186,12 +295,50 @@ impl Toggler {
     }
 }
 
+/// A `@cfg(...)` predicate.
+#[derive(Clone, Debug)]
+pub struct Cfg {
+    pub at_span: Span,
+    /// The parenthesized predicate, including the parentheses.
+    pub pred: TokenTree,
+}
+
+impl Cfg {
+    pub fn span(&self) -> Span {
+        self.at_span.join(self.pred.span()).unwrap_or(self.at_span)
+    }
+}
+
+/// A `@block name { ... }` inside an `@extends`.
+#[derive(Debug)]
+pub struct NamedBlock {
+    pub name: TokenTree,
+    pub body: Block,
+}
+
 #[derive(Debug)]
 pub struct MatchArm {
     pub head: TokenStream,
     pub body: Block,
 }
 
+/// Returns the text of an element or attribute name.
+///
+/// Names are usually identifiers joined by `-` and `:`, but attributes
+/// can also be quoted, as in `"@click"`.
+pub fn name_string(name: &TokenStream) -> String {
+    name.clone()
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Literal(ref lit) => match syn::parse_str::<syn::LitStr>(&lit.to_string()) {
+                Ok(lit) => lit.value(),
+                Err(_) => lit.to_string(),
+            },
+            token => token.to_string(),
+        })
+        .collect()
+}
+
 pub fn span_tokens<I: IntoIterator<Item=TokenTree>>(tokens: I) -> Span {
     join_spans(tokens.into_iter().map(|token| token.span()))
 }
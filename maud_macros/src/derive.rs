@@ -0,0 +1,118 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+use proc_macro::quote;
+use quote::ToTokens;
+use syn::{DeriveInput, LitStr};
+
+use crate::ParseResult;
+use crate::options::Options;
+
+/// Where a derived `Render` gets its markup from.
+enum Template {
+    /// A template body, as in `#[template(html = "...")]`.
+    Html(TokenStream),
+    /// A method that returns something to render, as in
+    /// `#[template(method = "...")]`.
+    Method(Ident),
+}
+
+/// Expands `#[derive(Render)]`.
+pub fn render(input: TokenStream) -> TokenStream {
+    let input = match syn::parse::<DeriveInput>(input) {
+        Ok(input) => input,
+        Err(e) => {
+            Span::call_site().error(format!("couldn't parse the item to derive `Render` for: {}", e)).emit();
+            return TokenStream::new();
+        },
+    };
+    let template = match template(&input) {
+        Ok(template) => template,
+        Err(()) => return TokenStream::new(),
+    };
+    let buffer = TokenTree::Ident(Ident::new("__maud_buffer", Span::def_site()));
+    // The template refers to `self` with the span of the derive
+    let self_ident = Ident::new("self", Span::call_site());
+    let body = match template {
+        Template::Html(body) => {
+            let writer = TokenStream::from(buffer.clone());
+            let body = crate::expand_to_writer(writer, body, Options::default());
+            // Writing to a `String` can't fail
+            quote!(let _ = $body;)
+        },
+        Template::Method(method) => quote!(::maud::Render::render_to(&$self_ident.$method(), $buffer);),
+    };
+    let name: TokenStream = input.ident.into_token_stream().into();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let impl_generics: TokenStream = impl_generics.into_token_stream().into();
+    let ty_generics: TokenStream = ty_generics.into_token_stream().into();
+    let where_clause: TokenStream = where_clause.into_token_stream().into();
+    quote!(
+        impl $impl_generics ::maud::Render for $name $ty_generics $where_clause {
+            fn render_to(&$self_ident, $buffer: &mut ::std::string::String) {
+                $body
+            }
+        }
+    )
+}
+
+/// Finds the `#[template(...)]` attribute of the item.
+fn template(input: &DeriveInput) -> ParseResult<Template> {
+    let attr = input.attrs.iter().find(|attr| {
+        attr.path.segments.len() == 1 && attr.path.segments[0].ident == "template"
+    });
+    let tokens: TokenStream = match attr {
+        Some(attr) => attr.tts.clone().into(),
+        None => {
+            Span::call_site()
+                .error("expected a `#[template(html = \"...\")]` or `#[template(method = \"...\")]` attribute")
+                .emit();
+            return Err(());
+        },
+    };
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let args = match tokens.as_slice() {
+        [TokenTree::Group(ref group)] if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream().into_iter().collect::<Vec<_>>()
+        },
+        _ => Vec::new(),
+    };
+    let (key, lit) = match args.as_slice() {
+        [TokenTree::Ident(ref key), TokenTree::Punct(ref eq), TokenTree::Literal(ref lit)] if eq.as_char() == '=' => {
+            (key, lit)
+        },
+        _ => {
+            let span = if tokens.is_empty() { Span::call_site() } else { crate::ast::span_tokens(tokens.clone()) };
+            span.error("expected `html = \"...\"` or `method = \"...\"`").emit();
+            return Err(());
+        },
+    };
+    let value = match syn::parse_str::<LitStr>(&lit.to_string()) {
+        Ok(value) => value.value(),
+        Err(_) => {
+            lit.span().error("expected a string literal").emit();
+            return Err(());
+        },
+    };
+    match key.to_string().as_str() {
+        "html" => match value.parse::<TokenStream>() {
+            Ok(body) => Ok(Template::Html(body)),
+            Err(e) => {
+                lit.span().error(format!("couldn't parse the template: {}", e)).emit();
+                Err(())
+            },
+        },
+        "method" if is_ident(&value) => Ok(Template::Method(Ident::new(&value, lit.span()))),
+        "method" => {
+            lit.span().error(format!("`{}` isn't a method name", value)).emit();
+            Err(())
+        },
+        other => {
+            key.span().error(format!("unknown template kind `{}`", other)).emit();
+            Err(())
+        },
+    }
+}
+
+fn is_ident(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
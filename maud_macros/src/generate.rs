@@ -11,25 +11,116 @@ use proc_macro::{
     TokenTree,
 };
 
+use std::fmt::Write;
+
 use crate::ast::*;
+use crate::options::Options;
 
-pub fn generate(markups: Vec<Markup>, output_ident: TokenTree) -> TokenStream {
-    let mut build = Builder::new(output_ident.clone());
-    Generator::new(output_ident).markups(markups, &mut build);
-    build.finish()
+pub fn generate(markups: Vec<Markup>, output: Output, options: Options) -> TokenStream {
+    generate_sized(markups, output, options).0
 }
 
-struct Generator {
+/// The size of a template's output, as far as it's known at compile time.
+pub struct SizeHint {
+    /// The number of bytes that are always written.
+    pub static_len: usize,
+    /// Whether the template writes nothing else, so `static_len` is the
+    /// exact size.
+    pub exact: bool,
+}
+
+/// Like `generate`, but also works out how much of the output is static.
+pub fn generate_sized(markups: Vec<Markup>, output: Output, options: Options) -> (TokenStream, SizeHint) {
+    let mut build = Builder::new(output.clone());
+    let escaper = options.escaper.clone();
+    Generator::new(output, options).markups(markups, &mut build);
+    let size_hint = SizeHint {
+        static_len: build.static_len + build.tail.len(),
+        exact: build.static_only,
+    };
+    let stmts = build.finish();
+    let stmts = match escaper {
+        Some(escaper) => {
+            let escaper_ident = escaper_ident();
+            quote!(let $escaper_ident: &dyn maud::Escape = &$escaper; $stmts)
+        },
+        None => stmts,
+    };
+    (stmts, size_hint)
+}
+
+/// Like `generate`, but renders each top-level node on its own, in an arm
+/// of a `match` on `index`. Returns the number of arms.
+pub fn generate_chunks(
+    markups: Vec<Markup>,
     output_ident: TokenTree,
+    index: TokenTree,
+    options: Options,
+) -> (TokenStream, usize) {
+    let output = Output::String(output_ident);
+    let generator = Generator::new(output.clone(), options);
+    let mut arms = Vec::new();
+    for (i, markup) in markups.into_iter().enumerate() {
+        let mut build = Builder::new(output.clone());
+        generator.markup(markup, &mut build);
+        let body = build.finish();
+        let i = TokenTree::Literal(Literal::usize_unsuffixed(i));
+        arms.push(quote!($i => { $body },));
+    }
+    let count = arms.len();
+    let arms = arms.into_iter().collect::<TokenStream>();
+    (quote!(match $index { $arms _ => {} }), count)
+}
+
+/// The destination that generated code writes to.
+#[derive(Clone)]
+pub enum Output {
+    /// A local `String` buffer.
+    String(TokenTree),
+    /// A `&mut fmt::Write`. Splices are rendered into `buffer` first, and
+    /// any error breaks out of the block labeled `label`.
+    Writer {
+        writer: TokenTree,
+        buffer: TokenTree,
+        label: TokenStream,
+    },
+}
+
+impl Output {
+    /// Generates a statement that appends the `&str` expression `string`.
+    fn push_str(&self, string: TokenStream) -> TokenStream {
+        match *self {
+            Output::String(ref output_ident) => {
+                let output_ident = output_ident.clone();
+                quote!($output_ident.push_str($string);)
+            },
+            Output::Writer { ref writer, ref label, .. } => {
+                let writer = writer.clone();
+                let label = label.clone();
+                quote!(
+                    if let ::std::result::Result::Err(__maud_error) =
+                        ::std::fmt::Write::write_str($writer, $string)
+                    {
+                        break $label ::std::result::Result::Err(__maud_error);
+                    }
+                )
+            },
+        }
+    }
+}
+
+struct Generator {
+    output: Output,
+    options: Options,
 }
 
 impl Generator {
-    fn new(output_ident: TokenTree) -> Generator {
-        Generator { output_ident }
+    fn new(output: Output, options: Options) -> Generator {
+        Generator { output, options }
     }
 
     fn builder(&self) -> Builder {
-        Builder::new(self.output_ident.clone())
+        Builder::new(self.output.clone())
     }
 
     fn markups(&self, markups: Vec<Markup>, build: &mut Builder) {
@@ -47,11 +138,26 @@ impl Generator {
                     self.markups(markups, build);
                 }
             },
-            Markup::Literal { content, .. } => build.push_escaped(&content),
+            Markup::Literal { content, .. } => self.text(&content, build),
+            Markup::ProcessingInstruction { content, .. } => {
+                build.push_str("<?");
+                build.push_str(&content);
+                build.push_str("?>");
+            },
+            Markup::Catch { at_span, body, fallback } => build.push_tokens(self.catch(body, fallback, at_span)),
+            Markup::ConditionalComment { condition, body, .. } => {
+                build.push_str("<!--[if ");
+                build.push_str(&condition);
+                build.push_str("]>");
+                self.markups(body.markups, build);
+                build.push_str("<![endif]-->");
+            },
             Markup::Symbol { symbol } => self.name(symbol, build),
-            Markup::Splice { expr, .. } => build.push_tokens(self.splice(expr)),
+            Markup::Write { writer, body, .. } => build.push_tokens(self.write(writer, body)),
+            Markup::Splice { expr, outer_span } => build.push_tokens(self.splice(expr, outer_span)),
             Markup::Element { name, attrs, body } => self.element(name, attrs, body, build),
             Markup::Let { tokens, .. } => build.push_tokens(tokens),
+            Markup::Control { tokens, .. } => build.push_tokens(tokens),
             Markup::Special { segments } => {
                 for segment in segments {
                     build.push_tokens(self.special(segment));
@@ -68,6 +174,31 @@ impl Generator {
                     quote!($head $body)
                 });
             },
+            Markup::Separated { head, body, sep, .. } => {
+                let first = TokenTree::Ident(Ident::new("__maud_first", Span::def_site()));
+                let body = self.block(body);
+                let sep = self.block(sep);
+                build.push_tokens(quote!({
+                    let mut $first = true;
+                    $head {
+                        if !$first $sep
+                        $first = false;
+                        $body
+                    }
+                }));
+            },
+            Markup::Extends { at_span, layout, blocks, blocks_span } => {
+                let span = at_span.join(blocks_span).unwrap_or(at_span);
+                build.push_tokens(self.extends(layout, blocks, span));
+            },
+            Markup::Around { at_span, wrapper, body } => {
+                let span = at_span.join(body.span()).unwrap_or(at_span);
+                build.push_tokens(self.around(wrapper, body, span));
+            },
+            Markup::Cfg { cfg, body } => {
+                let body = self.block(body);
+                build.push_tokens(cfg_attr(cfg, body));
+            },
         }
     }
 
@@ -79,20 +210,159 @@ impl Generator {
         TokenStream::from(block)
     }
 
-    fn splice(&self, expr: TokenStream) -> TokenStream {
-        let output_ident = self.output_ident.clone();
+    fn splice(&self, expr: TokenStream, outer_span: Span) -> TokenStream {
+        // Wrap the expression in parentheses, so that e.g. `(a + b)` renders
+        // the sum rather than calling a method on `b`
+        let mut expr = TokenTree::Group(Group::new(Delimiter::Parenthesis, expr));
+        expr.set_span(outer_span);
+        let expr = TokenStream::from(expr);
+        let escaper = self.escaper();
+        match self.output {
+            Output::String(ref output_ident) => {
+                trace(render_to(expr, output_ident.clone(), escaper), output_ident.clone(), outer_span)
+            },
+            Output::Writer { ref buffer, .. } => {
+                let buffer = buffer.clone();
+                let render = trace(render_to(expr, buffer.clone(), escaper), buffer.clone(), outer_span);
+                let write = self.output.push_str(quote!(&$buffer));
+                quote!({
+                    $buffer.clear();
+                    $render
+                    $write
+                })
+            },
+        }
+    }
+
+    /// The escaper set with `escaper = ...`, if any.
+    fn escaper(&self) -> Option<TokenTree> {
+        self.options.escaper.as_ref().map(|_| escaper_ident())
+    }
+
+    /// Writes text, escaping it at compile time unless there's a custom
+    /// escaper.
+    fn text(&self, text: &str, build: &mut Builder) {
+        if text.is_empty() {
+            return;
+        }
+        let escaper = match self.escaper() {
+            Some(escaper) => escaper,
+            None => return build.push_escaped(text),
+        };
+        let text = TokenTree::Literal(Literal::string(text));
+        build.push_tokens(match self.output {
+            Output::String(ref output_ident) => {
+                let output_ident = output_ident.clone();
+                quote!(maud::Escape::escape_to($escaper, $text, &mut $output_ident);)
+            },
+            Output::Writer { ref buffer, .. } => {
+                let buffer = buffer.clone();
+                let write = self.output.push_str(quote!(&$buffer));
+                quote!({
+                    $buffer.clear();
+                    maud::Escape::escape_to($escaper, $text, &mut $buffer);
+                    $write
+                })
+            },
+        });
+    }
+
+    /// Runs a `@write` block as a closure, so that `?` works inside it.
+    fn write(&self, writer: Ident, body: TokenTree) -> TokenStream {
+        let write_ident = TokenTree::Ident(Ident::new("__maud_write", Span::def_site()));
+        // The block may end in `return` or a lone expression, which would
+        // otherwise set off lints that point at the caller's code
+        let write = quote!(
+            #[allow(unreachable_code, unused_braces)]
+            let $write_ident = |$writer: &mut dyn ::std::fmt::Write| -> ::std::fmt::Result {
+                $body
+                ::std::result::Result::Ok(())
+            };
+        );
+        let call = match self.output {
+            // Like `Render` for `Display` types, errors can't go anywhere
+            Output::String(ref output_ident) => {
+                let output_ident = output_ident.clone();
+                quote!(let _ = $write_ident(&mut $output_ident);)
+            },
+            Output::Writer { ref writer, ref label, .. } => {
+                let writer = writer.clone();
+                let label = label.clone();
+                quote!(
+                    if let ::std::result::Result::Err(__maud_error) = $write_ident(&mut *$writer) {
+                        break $label ::std::result::Result::Err(__maud_error);
+                    }
+                )
+            },
+        };
+        quote!({ $write $call })
+    }
+
+    fn extends(&self, layout: TokenStream, blocks: Vec<NamedBlock>, span: Span) -> TokenStream {
+        let blocks_ident = TokenTree::Ident(Ident::new("__maud_blocks", Span::def_site()));
+        let assignments = blocks
+            .into_iter()
+            .map(|NamedBlock { name, body, .. }| {
+                let blocks_ident = blocks_ident.clone();
+                let markup = self.render_block(body);
+                quote!($blocks_ident.$name = ::std::convert::Into::into($markup);)
+            })
+            .collect::<TokenStream>();
+        // Bind the result first, so that the splices inside the blocks
+        // don't see the trait alias from the outer splice
+        let markup_ident = TokenTree::Ident(Ident::new("__maud_extended", Span::def_site()));
+        let splice = self.splice(TokenStream::from(markup_ident.clone()), span);
         quote!({
-            // Create a local trait alias so that autoref works
-            trait Render: maud::Render {
-                fn __maud_render_to(&self, output_ident: &mut ::std::string::String) {
-                    maud::Render::render_to(self, output_ident);
-                }
-            }
-            impl<T: maud::Render> Render for T {}
-            $expr.__maud_render_to(&mut $output_ident);
+            let $markup_ident = maud::rt::extend($layout, |$blocks_ident| { $assignments });
+            $splice
         })
     }
 
+    fn around(&self, wrapper: TokenStream, body: Block, span: Span) -> TokenStream {
+        let markup = self.render_block(body);
+        let mut wrapper_group = Group::new(Delimiter::Parenthesis, wrapper.clone());
+        wrapper_group.set_span(span_tokens(wrapper));
+        let wrapper = TokenTree::Group(wrapper_group);
+        // As with `@extends`, bind the result before splicing it
+        let markup_ident = TokenTree::Ident(Ident::new("__maud_wrapped", Span::def_site()));
+        let splice = self.splice(TokenStream::from(markup_ident.clone()), span);
+        quote!({
+            let $markup_ident = $wrapper($markup);
+            $splice
+        })
+    }
+
+    /// Renders `body` to a separate `Markup` first, so that if it panics,
+    /// `fallback` can be written instead.
+    fn catch(&self, body: Block, fallback: Block, span: Span) -> TokenStream {
+        let markup = self.render_block(body);
+        let rendered = TokenTree::Ident(Ident::new("__maud_rendered", Span::def_site()));
+        let splice = self.splice(TokenStream::from(rendered.clone()), span);
+        let fallback = self.block(fallback);
+        quote!(
+            match maud::rt::catch_panic(|| $markup) {
+                ::std::option::Option::Some($rendered) => { $splice }
+                ::std::option::Option::None => $fallback
+            }
+        )
+    }
+
+    /// Renders a block into a separate `Markup` value.
+    fn render_block(&self, Block { markups, outer_span }: Block) -> TokenStream {
+        let output_ident = TokenTree::Ident(Ident::new("__maud_block_output", Span::def_site()));
+        let output = Output::String(output_ident.clone());
+        let mut build = Builder::new(output.clone());
+        Generator::new(output, self.options.clone()).markups(markups, &mut build);
+        let stmts = build.finish();
+        let mut block = TokenTree::Group(Group::new(Delimiter::Brace, quote!(
+            let mut $output_ident = ::std::string::String::new();
+            $stmts
+            maud::PreEscaped($output_ident)
+        )));
+        block.set_span(outer_span);
+        TokenStream::from(block)
+    }
+
     fn element(
         &self,
         name: TokenStream,
@@ -102,49 +372,290 @@ impl Generator {
     ) {
         build.push_str("<");
         self.name(name.clone(), build);
+        let mut attrs = attrs;
+        if let Some(ref nonce) = self.options.nonce {
+            let name = name_string(&name);
+            let has_nonce = attrs.iter().any(|attr| match *attr {
+                Attr::Attribute { ref attribute } => name_string(&attribute.name).eq_ignore_ascii_case("nonce"),
+                _ => false,
+            });
+            if (name.eq_ignore_ascii_case("script") || name.eq_ignore_ascii_case("style")) && !has_nonce {
+                let span = span_tokens(nonce.clone());
+                attrs.push(Attr::Attribute {
+                    attribute: Attribute {
+                        name: TokenStream::from(TokenTree::Ident(Ident::new("nonce", span))),
+                        attr_type: AttrType::Normal {
+                            value: Markup::Splice { expr: nonce.clone(), outer_span: span },
+                        },
+                        cfg: None,
+                    },
+                });
+            }
+        }
         self.attrs(attrs, build);
-        build.push_str(">");
-        if let ElementBody::Block { block } = body {
-            self.markups(block.markups, build);
-            build.push_str("</");
-            self.name(name, build);
-            build.push_str(">");
+        match body {
+            ElementBody::Void { .. } if self.options.xhtml => build.push_str(" />"),
+            ElementBody::Void { .. } => build.push_str(">"),
+            ElementBody::Block { block } => {
+                build.push_str(">");
+                self.markups(block.markups, build);
+                build.push_str("</");
+                self.name(name, build);
+                build.push_str(">");
+            },
         }
     }
 
     fn name(&self, name: TokenStream, build: &mut Builder) {
-        let string = name.into_iter().map(|token| token.to_string()).collect::<String>();
-        build.push_escaped(&string);
+        build.push_escaped(&name_string(&name));
     }
 
     fn attrs(&self, attrs: Attrs, build: &mut Builder) {
-        for Attribute { name, attr_type } in desugar_attrs(attrs) {
-            match attr_type {
-                AttrType::Normal { value } => {
-                    build.push_str(" ");
-                    self.name(name, build);
-                    build.push_str("=\"");
-                    self.markup(value, build);
-                    build.push_str("\"");
-                },
-                AttrType::Empty { toggler: None } => {
-                    build.push_str(" ");
-                    self.name(name, build);
-                },
-                AttrType::Empty { toggler: Some(toggler) } => {
-                    let head = desugar_toggler(toggler);
-                    build.push_tokens({
-                        let mut build = self.builder();
-                        build.push_str(" ");
-                        self.name(name, &mut build);
-                        let body = build.finish();
-                        quote!($head { $body })
-                    })
-                },
+        let (defaults, attrs): (Vec<_>, Vec<_>) = attrs
+            .into_iter()
+            .partition(|attr| matches!(attr, Attr::Defaults { .. }));
+        let mut attributes = desugar_attrs(attrs);
+        if self.options.sort_attrs {
+            attributes.sort_by_key(|attribute| name_string(&attribute.name).to_ascii_lowercase());
+        }
+        // The element's own attributes take the place of any defaults, as
+        // long as they're compiled in
+        let names = attributes
+            .iter()
+            .map(|attribute| {
+                let name = TokenTree::Literal(Literal::string(&name_string(&attribute.name)));
+                match attribute.cfg {
+                    Some(ref cfg) => cfg_attr(cfg.clone(), quote!($name,)),
+                    None => quote!($name,),
+                }
+            })
+            .collect::<TokenStream>();
+        // The names of the defaults aren't known until runtime, so they
+        // can't be sorted in with the rest here
+        if self.options.sort_attrs && !defaults.is_empty() {
+            build.push_tokens(self.sorted_attrs(attributes, names, defaults));
+            return;
+        }
+        for Attribute { name, attr_type, cfg } in attributes {
+            if let Some(cfg) = cfg {
+                let mut build_attr = self.builder();
+                self.attr(name, attr_type, &mut build_attr);
+                let body = build_attr.finish();
+                build.push_tokens(cfg_attr(cfg, quote!({ $body })));
+            } else {
+                self.attr(name, attr_type, build);
+            }
+        }
+        for attr in defaults {
+            if let Attr::Defaults { at_span, pairs, pairs_span } = attr {
+                let mut pairs = TokenTree::Group(Group::new(Delimiter::None, pairs));
+                pairs.set_span(pairs_span);
+                let names = names.clone();
+                let defaults = quote!(maud::rt::Defaults::new($pairs, &[$names]));
+                build.push_tokens(self.splice(defaults, at_span.join(pairs_span).unwrap_or(at_span)));
             }
         }
     }
 
+    /// Writes an element's own attributes and its `@defaults` into a
+    /// `maud::rt::SortedAttrs`, which puts them all in order.
+    fn sorted_attrs(&self, attributes: Vec<Attribute>, names: TokenStream, defaults: Vec<Attr>) -> TokenStream {
+        let attrs_ident = TokenTree::Ident(Ident::new("__maud_sorted_attrs", Span::def_site()));
+        let output_ident = TokenTree::Ident(Ident::new("__maud_attr_output", Span::def_site()));
+        let mut stmts = TokenStream::new();
+        for Attribute { name, attr_type, cfg } in attributes {
+            let name_string = TokenTree::Literal(Literal::string(&name_string(&name)));
+            let output = Output::String(output_ident.clone());
+            let mut build = Builder::new(output.clone());
+            Generator::new(output, self.options.clone()).attr(name, attr_type, &mut build);
+            let body = build.finish();
+            let attrs_ident = attrs_ident.clone();
+            let output_ident = output_ident.clone();
+            let push = quote!({
+                let mut $output_ident = ::std::string::String::new();
+                $body
+                $attrs_ident.push($name_string, $output_ident);
+            });
+            stmts.extend(match cfg {
+                Some(cfg) => cfg_attr(cfg, push),
+                None => push,
+            });
+        }
+        let escape = match self.escaper() {
+            Some(escaper) => quote!(::std::option::Option::Some($escaper)),
+            None => quote!(::std::option::Option::None),
+        };
+        let mut span = None;
+        for attr in defaults {
+            if let Attr::Defaults { at_span, pairs, pairs_span } = attr {
+                let mut pairs = TokenTree::Group(Group::new(Delimiter::None, pairs));
+                pairs.set_span(pairs_span);
+                let attrs_ident = attrs_ident.clone();
+                let names = names.clone();
+                let escape = escape.clone();
+                stmts.extend(quote!($attrs_ident.defaults(maud::rt::Defaults::new($pairs, &[$names]), $escape);));
+                span = span.or_else(|| Some(at_span.join(pairs_span).unwrap_or(at_span)));
+            }
+        }
+        let splice = self.splice(TokenStream::from(attrs_ident.clone()), span.unwrap_or_else(Span::call_site));
+        quote!({
+            let mut $attrs_ident = maud::rt::SortedAttrs::default();
+            $stmts
+            $splice
+        })
+    }
+
+    fn attr(&self, name: TokenStream, attr_type: AttrType, build: &mut Builder) {
+        match attr_type {
+            AttrType::Normal { value } => {
+                let value = match value {
+                    // `srcset=(pairs)` joins a list of `(url, descriptor)`
+                    Markup::Splice { expr, outer_span } if name_string(&name).eq_ignore_ascii_case("srcset") => {
+                        let mut expr = TokenTree::Group(Group::new(Delimiter::None, expr));
+                        expr.set_span(outer_span);
+                        let expr = quote!({
+                            #[allow(unused_imports)]
+                            use maud::rt::{SrcSetPairs as _, SrcSetRender as _};
+                            (&maud::rt::SrcSet(&$expr)).srcset()
+                        });
+                        Markup::Splice { expr, outer_span }
+                    },
+                    value => value,
+                };
+                build.push_str(" ");
+                self.name(name, build);
+                match value.static_value() {
+                    Some(ref value)
+                    if self.options.minify && self.options.escaper.is_none() && is_unquotable(value) => {
+                        build.push_str("=");
+                        build.push_str(value);
+                    },
+                    _ => {
+                        build.push_str("=\"");
+                        self.markup(value, build);
+                        build.push_str("\"");
+                    },
+                }
+            },
+            AttrType::Empty { toggler: None } => self.empty_attr(name, build),
+            AttrType::Empty { toggler: Some(toggler) } => {
+                let head = desugar_toggler(toggler);
+                build.push_tokens({
+                    let mut build = self.builder();
+                    self.empty_attr(name, &mut build);
+                    let body = build.finish();
+                    quote!($head { $body })
+                })
+            },
+            AttrType::ClassFlags { flags, span } => build.push_tokens(self.class_flags(name, flags, span)),
+            AttrType::Classes { classes } => {
+                build.push_str(" ");
+                self.name(name, build);
+                build.push_str("=\"");
+                build.push_tokens(self.classes(classes));
+                build.push_str("\"");
+            },
+        }
+    }
+
+    /// Collects a class list in source order, skipping classes that are
+    /// toggled off or empty.
+    fn classes(&self, classes: Vec<(Markup, Option<Toggler>)>) -> TokenStream {
+        let list = TokenTree::Ident(Ident::new("__maud_classes", Span::def_site()));
+        let escaper = self.escaper();
+        let pushes = classes
+            .into_iter()
+            .map(|(name, toggler)| {
+                let list = list.clone();
+                let class = match name {
+                    Markup::Splice { expr, outer_span } => {
+                        let mut expr = TokenTree::Group(Group::new(Delimiter::Parenthesis, expr));
+                        expr.set_span(outer_span);
+                        quote!(&$expr)
+                    },
+                    name => match name.static_value() {
+                        Some(value) if escaper.is_some() => {
+                            TokenStream::from(TokenTree::Literal(Literal::string(&value)))
+                        },
+                        Some(value) => {
+                            let mut escaped = String::new();
+                            Escaper::new(&mut escaped).write_str(&value).unwrap();
+                            let escaped = TokenTree::Literal(Literal::string(&escaped));
+                            quote!(&maud::PreEscaped($escaped))
+                        },
+                        None => {
+                            let span = name.span();
+                            let block = self.render_block(Block { markups: vec![name], outer_span: span });
+                            quote!(&$block)
+                        },
+                    },
+                };
+                let push = match escaper.clone() {
+                    Some(escaper) => quote!($list.push_escaped($class, $escaper);),
+                    None => quote!($list.push($class);),
+                };
+                match toggler {
+                    Some(toggler) => {
+                        let head = desugar_toggler(toggler);
+                        quote!($head { $push })
+                    },
+                    None => push,
+                }
+            })
+            .collect::<TokenStream>();
+        let splice = self.splice(TokenStream::from(list.clone()), Span::call_site());
+        quote!({
+            let mut $list = maud::rt::ClassList::new();
+            $pushes
+            $splice
+        })
+    }
+
+    /// Writes the classes whose flags are set, leaving out the attribute
+    /// if there are none.
+    fn class_flags(&self, name: TokenStream, flags: TokenStream, span: Span) -> TokenStream {
+        let first = TokenTree::Ident(Ident::new("__maud_first", Span::def_site()));
+        let class = TokenTree::Ident(Ident::new("__maud_class", Span::def_site()));
+        let open = {
+            let mut build = self.builder();
+            build.push_str(" ");
+            self.name(name, &mut build);
+            build.push_str("=\"");
+            build.finish()
+        };
+        let space = {
+            let mut build = self.builder();
+            build.push_str(" ");
+            build.finish()
+        };
+        let close = {
+            let mut build = self.builder();
+            build.push_str("\"");
+            build.finish()
+        };
+        let splice = self.splice(TokenStream::from(class.clone()), span);
+        quote!({
+            let mut $first = true;
+            for $class in maud::rt::class_flags($flags) {
+                if $first { $open } else { $space }
+                $first = false;
+                $splice
+            }
+            if !$first { $close }
+        })
+    }
+
+    fn empty_attr(&self, name: TokenStream, build: &mut Builder) {
+        build.push_str(" ");
+        self.name(name.clone(), build);
+        if self.options.xhtml {
+            // XHTML has no empty attributes, so repeat the name instead
+            build.push_str("=\"");
+            self.name(name, build);
+            build.push_str("\"");
+        }
+    }
+
     fn special(&self, Special { head, body, .. }: Special) -> TokenStream {
         let body = self.block(body);
         quote!($head $body)
@@ -158,52 +669,101 @@ impl Generator {
 
 ////////////////////////////////////////////////////////
 
+/// Reports the text that `render` appends to `buffer` to `maud::trace`.
+#[cfg(feature = "trace")]
+fn trace(render: TokenStream, buffer: TokenTree, span: Span) -> TokenStream {
+    let start = TokenTree::Ident(Ident::new("__maud_trace_start", Span::def_site()));
+    let file = TokenTree::Literal(Literal::string(&span.file()));
+    let line = TokenTree::Literal(Literal::u32_suffixed(span.line() as u32));
+    let column = TokenTree::Literal(Literal::u32_suffixed(span.column() as u32));
+    quote!({
+        let $start = $buffer.len();
+        $render
+        if maud::trace::enabled() {
+            maud::trace::splice($file, $line, $column, &$buffer[$start..]);
+        }
+    })
+}
+
+#[cfg(not(feature = "trace"))]
+fn trace(render: TokenStream, _buffer: TokenTree, _span: Span) -> TokenStream {
+    render
+}
+
+fn escaper_ident() -> TokenTree {
+    TokenTree::Ident(Ident::new("__maud_escaper", Span::def_site()))
+}
+
+fn render_to(expr: TokenStream, buffer: TokenTree, escaper: Option<TokenTree>) -> TokenStream {
+    if let Some(escaper) = escaper {
+        return quote!({
+            trait Render: maud::Render {
+                fn __maud_render_to(
+                    &self,
+                    output_ident: &mut ::std::string::String,
+                    escaper: &dyn maud::Escape,
+                ) {
+                    maud::Render::render_escaped_to(self, output_ident, escaper);
+                }
+            }
+            impl<T: maud::Render> Render for T {}
+            $expr.__maud_render_to(&mut $buffer, $escaper);
+        });
+    }
+    quote!({
+        // Create a local trait alias so that autoref works
+        trait Render: maud::Render {
+            fn __maud_render_to(&self, output_ident: &mut ::std::string::String) {
+                maud::Render::render_to(self, output_ident);
+            }
+        }
+        impl<T: maud::Render> Render for T {}
+        $expr.__maud_render_to(&mut $buffer);
+    })
+}
+
 fn desugar_attrs(attrs: Attrs) -> Vec<Attribute> {
-    let mut classes_static = vec![];
-    let mut classes_toggled = vec![];
+    let mut classes = vec![];
     let mut ids = vec![];
     let mut attributes = vec![];
     for attr in attrs {
         match attr {
-            Attr::Class { name, toggler, .. } => {
-                if let Some(toggler) = toggler {
-                    classes_toggled.push((name, toggler));
-                } else {
-                    classes_static.push(name);
-                }
-            },
+            Attr::Class { name, toggler, .. } => classes.push((name, toggler)),
             Attr::Id { name, .. } => ids.push(name),
             Attr::Attribute { attribute } => attributes.push(attribute),
+            Attr::Defaults { .. } => unreachable!("defaults are written separately"),
         }
     }
-    let classes = desugar_classes_or_ids("class", classes_static, classes_toggled);
-    let ids = desugar_classes_or_ids("id", ids, vec![]);
+    let classes = desugar_classes(classes);
+    let ids = desugar_classes_or_ids("id", ids);
     classes.into_iter().chain(ids).chain(attributes).collect()
 }
 
-fn desugar_classes_or_ids(
-    attr_name: &'static str,
-    values_static: Vec<Markup>,
-    values_toggled: Vec<(Markup, Toggler)>,
-) -> Option<Attribute> {
-    if values_static.is_empty() && values_toggled.is_empty() {
+fn desugar_classes(classes: Vec<(Markup, Option<Toggler>)>) -> Option<Attribute> {
+    let is_static = classes.iter().all(|(name, toggler)| {
+        toggler.is_none() && name.static_value().is_some()
+    });
+    if is_static {
+        let names = classes.into_iter().map(|(name, _)| name).collect();
+        return desugar_classes_or_ids("class", names);
+    }
+    // Some classes are only known at runtime, so leave the spacing until then
+    Some(Attribute {
+        name: TokenStream::from(TokenTree::Ident(Ident::new("class", Span::call_site()))),
+        attr_type: AttrType::Classes { classes },
+        cfg: None,
+    })
+}
+
+fn desugar_classes_or_ids(attr_name: &'static str, values: Vec<Markup>) -> Option<Attribute> {
+    if values.is_empty() {
         return None;
     }
     let mut markups = Vec::new();
     let mut leading_space = false;
-    for name in values_static {
+    for name in values {
         markups.extend(prepend_leading_space(name, &mut leading_space));
     }
-    for (name, toggler) in values_toggled {
-        let body = Block {
-            markups: prepend_leading_space(name, &mut leading_space),
-            outer_span: toggler.cond_span,
-        };
-        let head = desugar_toggler(toggler);
-        markups.push(Markup::Special {
-            segments: vec![Special { at_span: Span::call_site(), head, body }],
-        });
-    }
     Some(Attribute {
         name: TokenStream::from(TokenTree::Ident(Ident::new(attr_name, Span::call_site()))),
         attr_type: AttrType::Normal {
@@ -212,6 +772,7 @@ fn desugar_classes_or_ids(
                 outer_span: Span::call_site(),
             }),
         },
+        cfg: None,
     })
 }
 
@@ -231,10 +792,10 @@ fn prepend_leading_space(name: Markup, leading_space: &mut bool) -> Vec<Markup>
 fn desugar_toggler(Toggler { mut cond, cond_span }: Toggler) -> TokenStream {
     // If the expression contains an opening brace `{`,
     // wrap it in parentheses to avoid parse errors
-    if cond.clone().into_iter().any(|token| match token {
-        TokenTree::Group(ref group) if group.delimiter() == Delimiter::Brace => true,
-        _ => false,
-    }) {
+    if cond.clone().into_iter().any(|token| matches!(
+        token,
+        TokenTree::Group(ref group) if group.delimiter() == Delimiter::Brace
+    )) {
         let mut wrapped_cond = TokenTree::Group(Group::new(Delimiter::Parenthesis, cond));
         wrapped_cond.set_span(cond_span);
         cond = TokenStream::from(wrapped_cond);
@@ -242,20 +803,38 @@ fn desugar_toggler(Toggler { mut cond, cond_span }: Toggler) -> TokenStream {
     quote!(if $cond)
 }
 
+/// Checks whether an attribute value can be written without quotes.
+fn is_unquotable(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| {
+        c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-'
+    })
+}
+
+fn cfg_attr(Cfg { pred, .. }: Cfg, body: TokenStream) -> TokenStream {
+    let cfg = TokenTree::Ident(Ident::new("cfg", pred.span()));
+    quote!(#[$cfg $pred] $body)
+}
+
 ////////////////////////////////////////////////////////
 
 struct Builder {
-    output_ident: TokenTree,
+    output: Output,
     tokens: Vec<TokenTree>,
     tail: String,
+    /// The length of the strings that have been cut from `tail`.
+    static_len: usize,
+    /// Whether `tokens` only writes static strings.
+    static_only: bool,
 }
 
 impl Builder {
-    fn new(output_ident: TokenTree) -> Builder {
+    fn new(output: Output) -> Builder {
         Builder {
-            output_ident,
+            output,
             tokens: Vec::new(),
             tail: String::new(),
+            static_len: 0,
+            static_only: true,
         }
     }
 
@@ -264,13 +843,13 @@ impl Builder {
     }
 
     fn push_escaped(&mut self, string: &str) {
-        use std::fmt::Write;
         Escaper::new(&mut self.tail).write_str(string).unwrap();
     }
 
     fn push_tokens<T: IntoIterator<Item=TokenTree>>(&mut self, tokens: T) {
         self.cut();
         self.tokens.extend(tokens);
+        self.static_only = false;
     }
 
     fn cut(&mut self) {
@@ -278,10 +857,10 @@ impl Builder {
             return;
         }
         let push_str_expr = {
-            let output_ident = self.output_ident.clone();
             let string = TokenTree::Literal(Literal::string(&self.tail));
-            quote!($output_ident.push_str($string);)
+            self.output.push_str(string.into())
         };
+        self.static_len += self.tail.len();
         self.tail.clear();
         self.tokens.extend(push_str_expr);
     }
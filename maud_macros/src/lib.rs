@@ -1,8 +1,8 @@
 #![feature(proc_macro_def_site)]
 #![feature(proc_macro_diagnostic)]
-#![feature(proc_macro_hygiene)]
 #![feature(proc_macro_quote)]
 #![feature(proc_macro_span)]
+#![feature(proc_macro_tracked_path)]
 
 #![doc(html_root_url = "https://docs.rs/maud_macros/0.20.0")]
 
@@ -13,11 +13,25 @@
 extern crate proc_macro;
 
 mod ast;
+mod derive;
 mod generate;
+mod lint;
+mod options;
 mod parse;
+#[cfg(feature = "hot-reload")]
+mod reload;
+mod source;
 
-use proc_macro::{Literal, Span, Ident, TokenStream, TokenTree};
+use proc_macro::{Delimiter, Group, Literal, Punct, Spacing, Span, Ident, TokenStream, TokenTree};
 use proc_macro::quote;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use syn::LitStr;
+
+use crate::generate::Output;
+use crate::options::Options;
+use crate::source::SourceFile;
 
 type ParseResult<T> = Result<T, ()>;
 
@@ -33,17 +47,257 @@ pub fn html_debug(input: TokenStream) -> TokenStream {
     expr
 }
 
-fn expand(input: TokenStream) -> TokenStream {
+#[proc_macro]
+pub fn html_string(input: TokenStream) -> TokenStream {
+    let markup = expand(input);
+    quote!(::std::string::String::from($markup))
+}
+
+#[proc_macro]
+pub fn html_chunks(input: TokenStream) -> TokenStream {
     let output_ident = TokenTree::Ident(Ident::new("__maud_output", Span::def_site()));
+    let index_ident = TokenTree::Ident(Ident::new("__maud_index", Span::def_site()));
+    let mut markups = parse::parse(input, &Options::default()).unwrap_or_default();
+    // Each chunk is rendered by a separate call, so a variable can't
+    // outlive the chunk it's declared in
+    let mut failed = false;
+    for markup in &markups {
+        if let ast::Markup::Let { .. } = *markup {
+            markup.span()
+                .error("`@let` can't be used at the top level of `html_chunks!`")
+                .help("wrap it in a block with the markup that uses it, like `{ @let x = ...; p { (x) } }`")
+                .emit();
+            failed = true;
+        }
+    }
+    if failed {
+        markups.clear();
+    }
+    let (arms, count) = generate::generate_chunks(
+        markups,
+        output_ident.clone(),
+        index_ident.clone(),
+        Options::default(),
+    );
+    let count = TokenTree::Literal(Literal::usize_unsuffixed(count));
+    quote!({
+        extern crate maud;
+        (0..$count)
+            .map(|$index_ident: usize| {
+                let mut $output_ident = ::std::string::String::new();
+                $arms
+                $output_ident
+            })
+            .filter(|chunk: &::std::string::String| !chunk.is_empty())
+    })
+}
+
+#[proc_macro]
+pub fn html_include(input: TokenStream) -> TokenStream {
+    let empty = quote!(::maud::PreEscaped(::std::string::String::new()));
+    let tokens = input.clone().into_iter().collect::<Vec<_>>();
+    let (path, path_span) = match tokens.as_slice() {
+        [TokenTree::Literal(ref lit)] => match syn::parse_str::<LitStr>(&lit.to_string()) {
+            Ok(path) => (path.value(), lit.span()),
+            Err(_) => {
+                lit.span().error("expected a string literal").emit();
+                return empty;
+            },
+        },
+        _ => {
+            let span = if tokens.is_empty() { Span::call_site() } else { ast::span_tokens(input) };
+            span.error("expected the path to a template file, like `\"header.maud\"`").emit();
+            return empty;
+        },
+    };
+    let path = resolve_include(&path);
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) => {
+            path_span.error(format!("couldn't read `{}`: {}", path.display(), e)).emit();
+            return empty;
+        },
+    };
+    let input = match source.parse::<TokenStream>() {
+        Ok(input) => input,
+        Err(e) => {
+            path_span.error(format!("couldn't parse `{}`: {}", path.display(), e)).emit();
+            return empty;
+        },
+    };
+    // The interpreter only sees this file, so it can't follow a `@partial`
+    #[cfg(feature = "hot-reload")]
+    let uses_partial = has_partial(input.clone());
+    // Tokens parsed from a string all take the span of the macro call, so
+    // point at the file by name when something goes wrong
+    let source_len = source.len();
+    let file = Rc::new(SourceFile::new(source));
+    let markups = parse::parse_file(input, path.clone(), &file, &Options::default()).unwrap_or_else(|()| {
+        for location in parse::file_locations(&path, &file) {
+            path_span.note(format!("in template file `{}`", location)).emit();
+        }
+        Vec::new()
+    });
+    #[cfg(feature = "hot-reload")]
+    {
+        let variables = if uses_partial {
+            None
+        } else {
+            reload::variables(&markups)
+        };
+        if let Some(variables) = variables {
+            return expand_reload(&path, variables);
+        }
+    }
+    // Rebuild when the template changes
+    proc_macro::tracked::path(&path);
+    expand_to_string(markups, source_len, Options::default())
+}
+
+/// Loads an included template at runtime, passing in the variables that
+/// it splices.
+#[cfg(feature = "hot-reload")]
+fn expand_reload(path: &std::path::Path, variables: Vec<Ident>) -> TokenStream {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = TokenTree::Literal(Literal::string(&path.to_string_lossy()));
+    let vars = variables
+        .into_iter()
+        .map(|variable| {
+            let name = TokenTree::Literal(Literal::string(&variable.to_string()));
+            quote!(($name, &$variable as &dyn maud::Render),)
+        })
+        .collect::<TokenStream>();
+    quote!({
+        extern crate maud;
+        maud::reload::render_file($path, &[$vars])
+    })
+}
+
+/// Checks whether a template uses `@partial`.
+#[cfg(feature = "hot-reload")]
+fn has_partial(input: TokenStream) -> bool {
+    let tokens = input.into_iter().collect::<Vec<_>>();
+    tokens.iter().enumerate().any(|(i, token)| match *token {
+        TokenTree::Punct(ref punct) if punct.as_char() == '@' => {
+            matches!(tokens.get(i + 1), Some(TokenTree::Ident(ref ident)) if ident.to_string() == "partial")
+        },
+        TokenTree::Group(ref group) => has_partial(group.stream()),
+        _ => false,
+    })
+}
+
+/// Resolves an included path relative to the file that includes it.
+pub(crate) fn resolve_include(path: &str) -> PathBuf {
+    let base = Span::call_site().local_file()
+        .and_then(|file| file.parent().map(|dir| dir.to_path_buf()))
+        .or_else(|| std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from))
+        .unwrap_or_default();
+    base.join(path)
+}
+
+#[proc_macro_derive(Render, attributes(template))]
+pub fn derive_render(input: TokenStream) -> TokenStream {
+    derive::render(input)
+}
+
+#[proc_macro]
+pub fn template(input: TokenStream) -> TokenStream {
+    let mut tokens = input.into_iter().collect::<Vec<_>>();
+    let body = match tokens.pop() {
+        Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        _ => {
+            Span::call_site().error("expected a function signature followed by a body").emit();
+            return TokenStream::new();
+        },
+    };
+    if !tokens.iter().any(|token| is_ident(token, "fn")) {
+        Span::call_site().error("expected a function signature, like `fn name(args)`").emit();
+        return TokenStream::new();
+    }
+    // The return type goes between the parameters and any `where` clause.
+    // Bounds like `F: Fn() -> u8` can have arrows of their own, so skip
+    // over the generics to find the parameters
+    let where_index = tokens.iter().position(|token| is_ident(token, "where")).unwrap_or(tokens.len());
+    let params_index = params_index(&tokens).unwrap_or(where_index);
+    let arrow = tokens[params_index.min(where_index)..where_index].windows(2).find(|pair| {
+        is_punct(&pair[0], '-') && is_punct(&pair[1], '>')
+    });
+    if let Some(arrow) = arrow {
+        let span = arrow[0].span().join(arrow[1].span()).unwrap_or_else(|| arrow[0].span());
+        span.error("templates always return `Markup`, so leave out the return type").emit();
+        return TokenStream::new();
+    }
+    let where_clause = tokens.split_off(where_index).into_iter().collect::<TokenStream>();
+    let signature = tokens.into_iter().collect::<TokenStream>();
+    let body = expand(body);
+    quote!($signature -> ::maud::Markup $where_clause { $body })
+}
+
+/// Finds the parameter list of a function signature, after the name and
+/// any generics.
+fn params_index(tokens: &[TokenTree]) -> Option<usize> {
+    let fn_index = tokens.iter().position(|token| is_ident(token, "fn"))?;
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(fn_index + 1) {
+        match *token {
+            TokenTree::Punct(ref punct) if punct.as_char() == '<' => depth += 1,
+            // The `>` of an `->` doesn't close anything
+            TokenTree::Punct(ref punct) if punct.as_char() == '>' && !is_punct(&tokens[i - 1], '-') => {
+                depth = depth.saturating_sub(1);
+            },
+            TokenTree::Group(ref group) if depth == 0 && group.delimiter() == Delimiter::Parenthesis => {
+                return Some(i);
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+fn is_ident(token: &TokenTree, name: &str) -> bool {
+    matches!(*token, TokenTree::Ident(ref ident) if ident.to_string() == name)
+}
+
+fn is_punct(token: &TokenTree, c: char) -> bool {
+    matches!(*token, TokenTree::Punct(ref punct) if punct.as_char() == c)
+}
+
+fn expand(input: TokenStream) -> TokenStream {
+    let (options, input) = options::parse(input);
+    if let Some(writer) = options.writer.clone() {
+        if options.pooled {
+            ast::span_tokens(writer.clone()).error("`pooled = true` can't be used with a writer").emit();
+        }
+        return expand_to_writer(writer, input, options);
+    }
     // Heuristic: the size of the resulting markup tends to correlate with the
     // code size of the template itself
     let size_hint = input.to_string().len();
-    let size_hint = TokenTree::Literal(Literal::u64_unsuffixed(size_hint as u64));
-    let markups = match parse::parse(input) {
-        Ok(markups) => markups,
-        Err(()) => Vec::new(),
+    let markups = parse::parse(input, &options).unwrap_or_default();
+    expand_to_string(markups, size_hint, options)
+}
+
+fn expand_to_string(markups: Vec<ast::Markup>, size_hint: usize, options: Options) -> TokenStream {
+    let output_ident = TokenTree::Ident(Ident::new("__maud_output", Span::def_site()));
+    lint::check(&markups, &options.lints);
+    let pooled = options.pooled;
+    let (stmts, static_size) = generate::generate_sized(markups, Output::String(output_ident.clone()), options);
+    // The output is at least as long as its static parts, and exactly as
+    // long if there's nothing else
+    let size_hint = if static_size.exact {
+        static_size.static_len
+    } else {
+        size_hint.max(static_size.static_len)
     };
-    let stmts = generate::generate(markups, output_ident.clone());
+    let size_hint = TokenTree::Literal(Literal::u64_unsuffixed(size_hint as u64));
+    if pooled {
+        return quote!({
+            extern crate maud;
+            let mut $output_ident = maud::rt::take_pooled($size_hint);
+            $stmts
+            maud::rt::into_pooled($output_ident)
+        });
+    }
     quote!({
         extern crate maud;
         let mut $output_ident = ::std::string::String::with_capacity($size_hint);
@@ -51,3 +305,28 @@ fn expand(input: TokenStream) -> TokenStream {
         maud::PreEscaped($output_ident)
     })
 }
+
+fn expand_to_writer(writer: TokenStream, input: TokenStream, options: Options) -> TokenStream {
+    let mut writer_group = Group::new(Delimiter::Parenthesis, writer.clone());
+    writer_group.set_span(ast::span_tokens(writer));
+    let buffer = TokenTree::Ident(Ident::new("__maud_splice_buffer", Span::def_site()));
+    let label: TokenStream = vec![
+        TokenTree::Punct(Punct::new('\'', Spacing::Joint)),
+        TokenTree::Ident(Ident::new("__maud_write", Span::def_site())),
+    ].into_iter().collect();
+    let markups = parse::parse(input, &options).unwrap_or_default();
+    lint::check(&markups, &options.lints);
+    let stmts = generate::generate(markups, Output::Writer {
+        writer: TokenTree::Group(writer_group),
+        buffer: buffer.clone(),
+        label: label.clone(),
+    }, options);
+    quote!({
+        extern crate maud;
+        let mut $buffer = maud::rt::SpliceBuffer::new();
+        $label: {
+            $stmts
+            ::std::result::Result::<(), ::std::fmt::Error>::Ok(())
+        }
+    })
+}
@@ -0,0 +1,317 @@
+use proc_macro::{Span, TokenStream, TokenTree};
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+
+/// An opt-in check on the contents of a template.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// Attributes like `onclick`, which are blocked by most content
+    /// security policies.
+    InlineEventHandlers,
+    /// `href` and `src` values that run code, like `javascript:`, or
+    /// that carry a document, like `data:text/html`.
+    UnsafeUrls,
+    /// `img` elements without an `alt` attribute.
+    MissingAlt,
+    /// `a` elements without an `href` attribute.
+    MissingHref,
+    /// `input` elements with an `id` that no `label` points to.
+    MissingLabel,
+    /// `html` elements without a `lang` attribute.
+    MissingLang,
+}
+
+impl Lint {
+    /// Looks up a lint, or a group of lints, by name.
+    fn from_name(name: &str) -> Option<&'static [Lint]> {
+        match name {
+            "inline_event_handlers" => Some(&[Lint::InlineEventHandlers]),
+            "unsafe_urls" => Some(&[Lint::UnsafeUrls]),
+            "security" => Some(&[Lint::InlineEventHandlers, Lint::UnsafeUrls]),
+            "missing_alt" => Some(&[Lint::MissingAlt]),
+            "missing_href" => Some(&[Lint::MissingHref]),
+            "missing_label" => Some(&[Lint::MissingLabel]),
+            "missing_lang" => Some(&[Lint::MissingLang]),
+            "accessibility" => Some(&[
+                Lint::MissingAlt,
+                Lint::MissingHref,
+                Lint::MissingLabel,
+                Lint::MissingLang,
+            ]),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The level of each lint. Lints are allowed unless enabled.
+#[derive(Clone, Debug, Default)]
+pub struct Lints {
+    levels: HashMap<Lint, Level>,
+}
+
+impl Lints {
+    /// Sets the level of the lints named in `names`, as in `warn(names)`.
+    pub fn set(&mut self, level: Level, names: TokenStream) {
+        for token in names {
+            match token {
+                TokenTree::Ident(ident) => match Lint::from_name(&ident.to_string()) {
+                    Some(lints) => {
+                        for &lint in lints {
+                            self.levels.insert(lint, level);
+                        }
+                    },
+                    None => ident.span().error(format!("unknown lint `{}`", ident)).emit(),
+                },
+                TokenTree::Punct(ref punct) if punct.as_char() == ',' => {},
+                token => token.span().error("expected a lint name").emit(),
+            }
+        }
+    }
+
+    fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).cloned().unwrap_or(Level::Allow)
+    }
+
+    fn report(&self, lint: Lint, span: Span, message: String, help: &str) {
+        let diagnostic = match self.level(lint) {
+            Level::Allow => return,
+            Level::Warn => span.warning(message),
+            Level::Deny => span.error(message),
+        };
+        diagnostic.help(help).emit();
+    }
+}
+
+/// Runs the enabled lints over a template.
+pub fn check(markups: &[Markup], lints: &Lints) {
+    if lints.levels.values().all(|&level| level == Level::Allow) {
+        return;
+    }
+    // `label for` can point to an input anywhere in the template, so find
+    // them all first
+    let mut labels = Some(HashSet::new());
+    walk(markups, &mut |name, attrs| {
+        if name_string(name) == "label" {
+            if let Some(AttrType::Normal { ref value }) = find_attribute(attrs, "for").map(|attribute| &attribute.attr_type) {
+                match value.static_value() {
+                    Some(target) => {
+                        if let Some(ref mut labels) = labels {
+                            labels.insert(target);
+                        }
+                    },
+                    // If any `for` is spliced, any input could be labeled
+                    None => labels = None,
+                }
+            }
+        }
+    });
+    let checker = Checker { lints, labels };
+    walk(markups, &mut |name, attrs| checker.element(name, attrs));
+}
+
+/// Calls `f` with the name and attributes of every element.
+fn walk(markups: &[Markup], f: &mut dyn FnMut(&TokenStream, &Attrs)) {
+    for markup in markups {
+        match *markup {
+            Markup::Block(ref block) => walk(&block.markups, f),
+            Markup::Element { ref name, ref attrs, ref body } => {
+                f(name, attrs);
+                if let ElementBody::Block { ref block } = *body {
+                    walk(&block.markups, f);
+                }
+            },
+            Markup::Special { ref segments } => {
+                for segment in segments {
+                    walk(&segment.body.markups, f);
+                }
+            },
+            Markup::Match { ref arms, .. } => {
+                for arm in arms {
+                    walk(&arm.body.markups, f);
+                }
+            },
+            Markup::Cfg { ref body, .. }
+            | Markup::ConditionalComment { ref body, .. }
+            | Markup::Around { ref body, .. } => walk(&body.markups, f),
+            Markup::Separated { ref body, ref sep, .. } => {
+                walk(&body.markups, f);
+                walk(&sep.markups, f);
+            },
+            Markup::Catch { ref body, ref fallback, .. } => {
+                walk(&body.markups, f);
+                walk(&fallback.markups, f);
+            },
+            Markup::Extends { ref blocks, .. } => {
+                for block in blocks {
+                    walk(&block.body.markups, f);
+                }
+            },
+            Markup::Literal { .. }
+            | Markup::ProcessingInstruction { .. }
+            | Markup::Write { .. }
+            | Markup::Symbol { .. }
+            | Markup::Splice { .. }
+            | Markup::Let { .. }
+            | Markup::Control { .. } => {},
+        }
+    }
+}
+
+fn name_string(name: &TokenStream) -> String {
+    crate::ast::name_string(name).to_ascii_lowercase()
+}
+
+fn find_attribute<'a>(attrs: &'a Attrs, name: &str) -> Option<&'a Attribute> {
+    attrs.iter().filter_map(|attr| match *attr {
+        Attr::Attribute { ref attribute } => Some(attribute),
+        _ => None,
+    }).find(|attribute| name_string(&attribute.name) == name)
+}
+
+struct Checker<'a> {
+    lints: &'a Lints,
+    /// The targets of every `label for`, or `None` if some are spliced.
+    labels: Option<HashSet<String>>,
+}
+
+impl<'a> Checker<'a> {
+    fn element(&self, name: &TokenStream, attrs: &Attrs) {
+        for attr in attrs {
+            if let Attr::Attribute { ref attribute } = *attr {
+                self.attribute(attribute);
+            }
+        }
+        self.accessibility(name, attrs);
+    }
+
+    fn accessibility(&self, name: &TokenStream, attrs: &Attrs) {
+        // Attributes that may or may not be there can't be checked
+        let uncertain = attrs.iter().any(|attr| match *attr {
+            Attr::Attribute { ref attribute } => {
+                attribute.cfg.is_some() || matches!(
+                    attribute.attr_type,
+                    AttrType::Empty { toggler: Some(_) } | AttrType::ClassFlags { .. }
+                )
+            },
+            Attr::Defaults { .. } => true,
+            _ => false,
+        });
+        if uncertain {
+            return;
+        }
+        let span = span_tokens(name.clone());
+        let has = |attr_name: &str| find_attribute(attrs, attr_name).is_some();
+        let report = |lint: Lint, message: String, help: &str| {
+            self.lints.report(lint, span, message, help);
+        };
+        match name_string(name).as_str() {
+            "img" if !has("alt") => report(
+                Lint::MissingAlt,
+                "`img` without an `alt` attribute".to_string(),
+                "describe the image with `alt`, or use `alt=\"\"` if it's only decoration",
+            ),
+            "a" if !has("href") => report(
+                Lint::MissingHref,
+                "`a` without an `href` attribute".to_string(),
+                "add the link target, or use a `button` for actions",
+            ),
+            "html" if !has("lang") => report(
+                Lint::MissingLang,
+                "`html` without a `lang` attribute".to_string(),
+                "set the language of the page, like `lang=\"en\"`",
+            ),
+            "input" => {
+                let labels = match self.labels {
+                    Some(ref labels) => labels,
+                    None => return,
+                };
+                if has("aria-label") || has("aria-labelledby") {
+                    return;
+                }
+                // Buttons and hidden inputs don't need a label
+                let input_type = find_attribute(attrs, "type").and_then(|attribute| match attribute.attr_type {
+                    AttrType::Normal { ref value } => value.static_value(),
+                    _ => None,
+                });
+                if let Some("hidden") | Some("submit") | Some("reset") | Some("button") | Some("image")
+                    = input_type.as_deref() {
+                    return;
+                }
+                let id = attrs.iter().filter_map(|attr| match *attr {
+                    Attr::Id { ref name, .. } => Some(name.static_value()),
+                    _ => None,
+                }).chain(find_attribute(attrs, "id").map(|attribute| match attribute.attr_type {
+                    AttrType::Normal { ref value } => value.static_value(),
+                    _ => None,
+                })).next();
+                if let Some(Some(id)) = id {
+                    if !labels.contains(&id) {
+                        report(
+                            Lint::MissingLabel,
+                            format!("`input` with id `{}` has no `label`", id),
+                            "add a `label` with a matching `for` attribute",
+                        );
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn attribute(&self, attribute: &Attribute) {
+        let name = name_string(&attribute.name);
+        if name.len() > 2 && name.starts_with("on") {
+            self.lints.report(
+                Lint::InlineEventHandlers,
+                attribute.span(),
+                format!("inline event handler `{}`", name),
+                "use a `data-*` attribute and attach the handler from an external script",
+            );
+        }
+        if name == "href" || name == "src" {
+            if let AttrType::Normal { ref value } = attribute.attr_type {
+                let url = value.static_value().unwrap_or_default();
+                let url = url.trim_start().to_ascii_lowercase();
+                if url.starts_with("javascript:") {
+                    self.lints.report(
+                        Lint::UnsafeUrls,
+                        attribute.span(),
+                        format!("`javascript:` URL in `{}`", name),
+                        "link to a real page, and attach any behavior from an external script",
+                    );
+                } else if url.starts_with("data:") && !is_data_image(&url) {
+                    self.lints.report(
+                        Lint::UnsafeUrls,
+                        attribute.span(),
+                        format!("`data:` URL in `{}`", name),
+                        "serve the content from its own URL",
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Checks for a `data:` URL with a bitmap image, which can't run scripts.
+fn is_data_image(url: &str) -> bool {
+    url.starts_with("data:image/") && !url.starts_with("data:image/svg")
+}
@@ -0,0 +1,142 @@
+use proc_macro::{Delimiter, Group, Spacing, Span, TokenStream, TokenTree};
+
+use crate::ast;
+use crate::lint::{Level, Lints};
+
+/// Settings given before the body of a template, as in
+/// `html!(writer, minify = true, { ... })`.
+#[derive(Clone, Default)]
+pub struct Options {
+    /// Where to write the output, or `None` to build a `String`.
+    pub writer: Option<TokenStream>,
+    /// Leave out optional parts of the markup, like the quotes around
+    /// simple attribute values.
+    pub minify: bool,
+    /// Write void elements as `<br />` and empty attributes as
+    /// `checked="checked"`, for XHTML or XML output.
+    pub xhtml: bool,
+    /// Write each element's attributes in alphabetical order, so that the
+    /// output doesn't change when they're moved around in the source.
+    pub sort_attrs: bool,
+    /// Render into a `maud::PooledHtml` rather than a `Markup`.
+    pub pooled: bool,
+    /// A custom `maud::Escape` for text and splices, as in
+    /// `escaper = my_escape`.
+    pub escaper: Option<TokenStream>,
+    /// A content security policy nonce for every `script` and `style`, as
+    /// in `nonce = request.nonce`.
+    pub nonce: Option<TokenStream>,
+    /// Which lints to run, as set by `warn(...)`, `deny(...)`, and
+    /// `allow(...)`.
+    pub lints: Lints,
+}
+
+/// Splits the input into options and template body.
+///
+/// Returns default options if the input is a plain template.
+pub fn parse(input: TokenStream) -> (Options, TokenStream) {
+    let mut options = Options::default();
+    let mut tokens = input.clone().into_iter().collect::<Vec<_>>();
+    let body = match tokens.pop() {
+        Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        _ => return (options, input),
+    };
+    match tokens.pop() {
+        Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => {},
+        _ => return (options, input),
+    }
+    if tokens.is_empty() {
+        Span::call_site().error("expected a writer or option before `,`").emit();
+    }
+    for arg in split_args(tokens) {
+        options.arg(arg);
+    }
+    (options, body)
+}
+
+/// Checks whether the input has options before the template body.
+pub fn has_options(input: &TokenStream) -> bool {
+    let tokens = input.clone().into_iter().collect::<Vec<_>>();
+    match *tokens.as_slice() {
+        [.., TokenTree::Punct(ref comma), TokenTree::Group(ref body)] => {
+            comma.as_char() == ',' && body.delimiter() == Delimiter::Brace
+        },
+        _ => false,
+    }
+}
+
+/// Splits a list of tokens on top-level commas.
+fn split_args(tokens: Vec<TokenTree>) -> Vec<Vec<TokenTree>> {
+    let mut args = vec![Vec::new()];
+    for token in tokens {
+        match token {
+            TokenTree::Punct(ref punct) if punct.as_char() == ',' => args.push(Vec::new()),
+            token => args.last_mut().expect("args should be non-empty").push(token),
+        }
+    }
+    args
+}
+
+impl Options {
+    /// Whether a template with these options renders the same as one with
+    /// none. Where the output goes and which lints run don't count.
+    pub fn renders_like_default(&self) -> bool {
+        !self.minify && !self.xhtml && !self.sort_attrs && self.escaper.is_none() && self.nonce.is_none()
+    }
+
+    fn arg(&mut self, arg: Vec<TokenTree>) {
+        match (arg.first(), arg.get(1)) {
+            (Some(TokenTree::Ident(ref key)), Some(TokenTree::Punct(ref eq)))
+            if eq.as_char() == '=' && eq.spacing() == Spacing::Alone => {
+                let value = arg[2..].to_vec();
+                match key.to_string().as_str() {
+                    "minify" => self.minify = bool_value(key.span(), value),
+                    "xhtml" => self.xhtml = bool_value(key.span(), value),
+                    "sort_attrs" => self.sort_attrs = bool_value(key.span(), value),
+                    "pooled" => self.pooled = bool_value(key.span(), value),
+                    "escaper" if value.is_empty() => key.span().error("expected an escaper after `=`").emit(),
+                    "escaper" => {
+                        let mut escaper = Group::new(Delimiter::Parenthesis, value.iter().cloned().collect());
+                        escaper.set_span(ast::span_tokens(value));
+                        self.escaper = Some(TokenStream::from(TokenTree::Group(escaper)));
+                    },
+                    "nonce" if value.is_empty() => key.span().error("expected a nonce after `=`").emit(),
+                    "nonce" => {
+                        let mut nonce = Group::new(Delimiter::None, value.iter().cloned().collect());
+                        nonce.set_span(ast::span_tokens(value));
+                        self.nonce = Some(TokenStream::from(TokenTree::Group(nonce)));
+                    },
+                    other => {
+                        key.span().error(format!("unknown option `{}`", other)).emit();
+                    },
+                }
+            },
+            (Some(TokenTree::Ident(ref level)), Some(TokenTree::Group(ref names)))
+            if arg.len() == 2 && names.delimiter() == Delimiter::Parenthesis
+                && Level::from_name(&level.to_string()).is_some() => {
+                let level = Level::from_name(&level.to_string()).expect("lint level");
+                self.lints.set(level, names.stream());
+            },
+            (None, _) => Span::call_site().error("unexpected `,`").emit(),
+            _ => {
+                if self.writer.is_some() {
+                    ast::span_tokens(arg).error("a template can only have one writer").emit();
+                } else {
+                    self.writer = Some(arg.into_iter().collect());
+                }
+            },
+        }
+    }
+}
+
+fn bool_value(key_span: Span, value: Vec<TokenTree>) -> bool {
+    match value.as_slice() {
+        [TokenTree::Ident(ref ident)] if ident.to_string() == "true" => true,
+        [TokenTree::Ident(ref ident)] if ident.to_string() == "false" => false,
+        _ => {
+            let span = if value.is_empty() { key_span } else { ast::span_tokens(value) };
+            span.error("expected `true` or `false`").emit();
+            false
+        },
+    }
+}
@@ -1,63 +1,130 @@
 use proc_macro::{
     Delimiter,
+    Group,
     Ident,
     Literal,
+    Punct,
     Spacing,
     Span,
     TokenStream,
     TokenTree,
+    quote,
 };
 use std::collections::HashMap;
+use std::iter::Peekable;
+use std::fs;
 use std::mem;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 use syn::{LitStr, parse_str};
 
 use crate::ast;
+use crate::options::Options;
+use crate::source::SourceFile;
 use crate::ParseResult;
 
-pub fn parse(input: TokenStream) -> ParseResult<Vec<ast::Markup>> {
-    Parser::new(input).markups()
+pub fn parse(input: TokenStream, options: &Options) -> ParseResult<Vec<ast::Markup>> {
+    Parser::new(input, options).markups()
+}
+
+/// Parses a template read from `path`, so that any `@partial` in it is
+/// found relative to that file.
+pub fn parse_file(
+    input: TokenStream,
+    path: PathBuf,
+    file: &Rc<SourceFile>,
+    options: &Options,
+) -> ParseResult<Vec<ast::Markup>> {
+    let mut parser = Parser::new(input, options);
+    let real_path = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+    parser.partials = Rc::new([(path, real_path)]);
+    parser.source = file.offsets(&parser.input).map(|offsets| (file.clone(), offsets));
+    parser.markups()
+}
+
+/// Names the lines of `file` where parsing failed, like `header.maud:3`,
+/// or just the path if they aren't known.
+pub fn file_locations(path: &std::path::Path, file: &SourceFile) -> Vec<String> {
+    let lines = file.failed_lines();
+    if lines.is_empty() {
+        return vec![path.display().to_string()];
+    }
+    lines.into_iter().map(|line| format!("{}:{}", path.display(), line)).collect()
 }
 
 #[derive(Clone)]
 struct Parser {
     /// Indicates whether we're inside an attribute node.
     in_attr: bool,
-    input: <TokenStream as IntoIterator>::IntoIter,
+    /// The tokens being parsed. These are shared, so that backtracking
+    /// only has to copy the position.
+    input: Rc<[TokenTree]>,
+    /// The index of the next token in `input`.
+    pos: usize,
+    /// The `@partial` files being parsed, innermost last, each as written
+    /// and as a real path.
+    partials: Rc<[(PathBuf, PathBuf)]>,
+    /// For a template read from a file, the file and where each token in
+    /// `input` starts in it.
+    source: Option<(Rc<SourceFile>, Rc<[usize]>)>,
+    /// Whether a nested `(maud::html! { ... })` can be written in place.
+    /// This is only the case when the outer template renders the same way
+    /// the inner one would on its own.
+    inline_html: bool,
 }
 
 impl Iterator for Parser {
     type Item = TokenTree;
 
     fn next(&mut self) -> Option<TokenTree> {
-        self.input.next()
+        let token = self.input.get(self.pos).cloned();
+        if token.is_some() {
+            if let Some((ref file, ref offsets)) = self.source {
+                file.consumed(offsets[self.pos]);
+            }
+            self.pos += 1;
+        }
+        token
     }
 }
 
 impl Parser {
-    fn new(input: TokenStream) -> Parser {
+    fn new(input: TokenStream, options: &Options) -> Parser {
         Parser {
             in_attr: false,
-            input: input.into_iter(),
+            input: input.into_iter().collect(),
+            pos: 0,
+            partials: Rc::new([]),
+            source: None,
+            inline_html: options.renders_like_default(),
         }
     }
 
     fn with_input(&self, input: TokenStream) -> Parser {
+        let input: Rc<[TokenTree]> = input.into_iter().collect();
+        let source = self.source.as_ref().and_then(|(file, offsets)| {
+            let offsets = file.group_offsets(&self.input, offsets, self.pos, &input)?;
+            Some((file.clone(), offsets))
+        });
         Parser {
             in_attr: self.in_attr,
-            input: input.into_iter(),
+            input,
+            pos: 0,
+            partials: self.partials.clone(),
+            source,
+            inline_html: self.inline_html,
         }
     }
 
     /// Returns the next token in the stream without consuming it.
     fn peek(&mut self) -> Option<TokenTree> {
-        self.clone().next()
+        self.input.get(self.pos).cloned()
     }
 
     /// Returns the next two tokens in the stream without consuming them.
     fn peek2(&mut self) -> Option<(TokenTree, Option<TokenTree>)> {
-        let mut clone = self.clone();
-        clone.next().map(|first| (first, clone.next()))
+        self.peek().map(|first| (first, self.input.get(self.pos + 1).cloned()))
     }
 
     /// Advances the cursor by one step.
@@ -77,24 +144,69 @@ impl Parser {
     }
 
     /// Parses and renders multiple blocks of markup.
+    ///
+    /// If a markup fails to parse, this skips ahead to the next one and
+    /// keeps going, so that independent mistakes are reported together.
     fn markups(&mut self) -> ParseResult<Vec<ast::Markup>> {
         let mut result = Vec::new();
+        let mut failed = false;
         loop {
-            match self.peek2() {
+            let start = self.pos;
+            let failures = self.source.as_ref().map(|(file, _)| file.failure_count());
+            let markup = match self.peek2() {
                 None => break,
-                Some((TokenTree::Punct(ref punct), _)) if punct.as_char() == ';' => self.advance(),
+                Some((TokenTree::Punct(ref punct), _)) if punct.as_char() == ';' => {
+                    self.advance();
+                    continue;
+                },
                 Some((
                     TokenTree::Punct(ref punct),
                     Some(TokenTree::Ident(ref ident)),
                 )) if punct.as_char() == '@' && ident.to_string() == "let" => {
                     self.advance2();
                     let keyword = TokenTree::Ident(ident.clone());
-                    result.push(self.let_expr(punct.span(), keyword)?);
+                    self.let_expr(punct.span(), keyword)
+                },
+                _ => self.markup(),
+            };
+            match markup {
+                Ok(markup) => result.push(markup),
+                Err(()) => {
+                    failed = true;
+                    if let (Some((file, _)), Some(failures)) = (&self.source, failures) {
+                        file.fail(failures);
+                    }
+                    // Make sure we always make progress
+                    if self.pos == start {
+                        self.advance();
+                    }
+                    self.recover();
                 },
-                _ => result.push(self.markup()?),
             }
         }
-        Ok(result)
+        if failed {
+            Err(())
+        } else {
+            Ok(result)
+        }
+    }
+
+    /// Skips over tokens that can't start a markup.
+    ///
+    /// Errors have already been reported by this point, so this stops at
+    /// the next sibling rather than trying to make sense of the remains.
+    fn recover(&mut self) {
+        loop {
+            match self.peek() {
+                None
+                | Some(TokenTree::Literal(_))
+                | Some(TokenTree::Ident(_)) => break,
+                Some(TokenTree::Punct(ref punct))
+                if matches!(punct.as_char(), '@' | '.' | '#' | '<') => break,
+                Some(TokenTree::Group(ref group)) if group.delimiter() != Delimiter::Bracket => break,
+                Some(_) => self.advance(),
+            }
+        }
     }
 
     /// Parses and renders a single block of markup.
@@ -126,8 +238,37 @@ impl Parser {
                                 ast::Markup::Special { segments }
                             },
                             "while" => self.while_expr(at_span, keyword)?,
-                            "for" => self.for_expr(at_span, keyword)?,
+                            "for" | "for_sorted" => self.for_expr(at_span, keyword)?,
                             "match" => self.match_expr(at_span, keyword)?,
+                            "break" | "continue" => self.control_expr(at_span, keyword),
+                            "do" => self.do_expr(at_span, keyword)?,
+                            "cfg" => self.cfg_expr(at_span, keyword)?,
+                            "extends" => self.extends_expr(at_span, keyword)?,
+                            "around" => self.around_expr(at_span, keyword)?,
+                            "spaceless" => self.spaceless_expr(at_span, keyword)?,
+                            "env" => self.env_expr(at_span, keyword)?,
+                            "pi" => self.pi_expr(at_span, keyword)?,
+                            "ie_comment" => self.ie_comment_expr(at_span, keyword)?,
+                            "dl" => self.dl_expr(at_span, keyword)?,
+                            "table" => self.table_expr(at_span, keyword)?,
+                            "json_script" => self.json_script_expr(at_span, keyword)?,
+                            "partial" => self.partial_expr(at_span, keyword)?,
+                            "options" => self.options_expr(at_span, keyword)?,
+                            "text" => self.text_expr(at_span, keyword)?,
+                            "truncate" => self.truncate_expr(at_span, keyword)?,
+                            "write" => self.write_expr(at_span, keyword)?,
+                            "sep" | "separated_by" => {
+                                let ident_span = ident.span();
+                                let span = at_span.join(ident_span).unwrap_or(ident_span);
+                                span.error(format!("`@{}` only works after the body of a `@for`", ident)).emit();
+                                return Err(());
+                            },
+                            "block" => {
+                                let ident_span = ident.span();
+                                let span = at_span.join(ident_span).unwrap_or(ident_span);
+                                span.error("`@block` only works inside `@extends`").emit();
+                                return Err(());
+                            },
                             "let" => {
                                 let ident_span = ident.span();
                                 let span = at_span.join(ident_span).unwrap_or(ident_span);
@@ -153,7 +294,7 @@ impl Parser {
                 let ident_string = ident.to_string();
                 // Is this a keyword that's missing a '@'?
                 match ident_string.as_str() {
-                    "if" | "while" | "for" | "match" | "let" => {
+                    "if" | "while" | "for" | "match" | "let" | "break" | "continue" => {
                         ident.span()
                             .warning(format!("found keyword `{0}` - should this be a `@{0}`?", ident_string))
                             .emit();
@@ -173,13 +314,29 @@ impl Parser {
             // Splice
             TokenTree::Group(ref group) if group.delimiter() == Delimiter::Parenthesis => {
                 self.advance();
-                ast::Markup::Splice { expr: group.stream(), outer_span: group.span() }
+                let stream = group.stream();
+                match (split_else(stream.clone()), split_catch(stream.clone()), nested_html(stream.clone())) {
+                    (Some((value, fallback)), _, _) => self.splice_else(value, fallback, group.span())?,
+                    (None, Some((value, fallback)), _) => self.splice_catch(value, fallback, group.span())?,
+                    // Write a nested template in place, rather than building
+                    // a `Markup` only to copy it out again
+                    (None, None, Some(body)) if self.inline_html && !self.in_attr => {
+                        ast::Markup::Block(self.block(body.stream(), group.span())?)
+                    },
+                    _ => ast::Markup::Splice { expr: format_splice(stream), outer_span: group.span() },
+                }
             }
             // Block
             TokenTree::Group(ref group) if group.delimiter() == Delimiter::Brace => {
                 self.advance();
                 ast::Markup::Block(self.block(group.stream(), group.span())?)
             },
+            // Pasted HTML tag
+            TokenTree::Punct(ref punct) if punct.as_char() == '<' => {
+                self.advance();
+                self.angle_bracket_tag(punct.span());
+                return Err(());
+            },
             // ???
             token => {
                 token.span().error("invalid syntax").emit();
@@ -189,6 +346,37 @@ impl Parser {
         Ok(markup)
     }
 
+    /// Reports an HTML tag like `<div class="x">`, suggesting the Maud
+    /// equivalent where possible.
+    ///
+    /// The leading `<` should already be consumed. This consumes everything
+    /// up to and including the closing `>`.
+    fn angle_bracket_tag(&mut self, lt_span: Span) {
+        let mut tokens = Vec::new();
+        let mut span = lt_span;
+        loop {
+            match self.next() {
+                Some(TokenTree::Punct(ref punct)) if punct.as_char() == '>' => {
+                    span = span.join(punct.span()).unwrap_or(span);
+                    break;
+                },
+                Some(token) => {
+                    span = span.join(token.span()).unwrap_or(span);
+                    tokens.push(token);
+                },
+                None => break,
+            }
+        }
+        let diagnostic = span.error("Maud does not use angle-bracket syntax for elements");
+        match suggest_element(&tokens) {
+            Some(Suggestion::Close) => diagnostic.help("close elements with `}` instead"),
+            Some(Suggestion::Open(suggestion)) => {
+                diagnostic.help(format!("write `{}` instead", suggestion))
+            },
+            None => diagnostic.help("write elements like `p class=\"intro\" { ... }` instead"),
+        }.emit();
+    }
+
     /// Parses and renders a literal string.
     fn literal(&mut self, lit: &Literal) -> ParseResult<ast::Markup> {
         let content = parse_str::<LitStr>(&lit.to_string())
@@ -203,6 +391,60 @@ impl Parser {
         })
     }
 
+    /// Desugars `(value else fallback)` to a `match` that renders the
+    /// `Option` if it's `Some`, and the fallback markup otherwise.
+    fn splice_else(
+        &mut self,
+        value: TokenStream,
+        fallback: TokenStream,
+        outer_span: Span,
+    ) -> ParseResult<ast::Markup> {
+        let fallback_span = ast::span_tokens(fallback.clone());
+        let fallback = self.with_input(fallback).markups()?;
+        let binding = TokenTree::Ident(Ident::new("__maud_value", Span::def_site()));
+        let mut value_group = Group::new(Delimiter::Parenthesis, value.clone());
+        value_group.set_span(ast::span_tokens(value));
+        let value = TokenTree::Group(value_group);
+        let some = ast::MatchArm {
+            head: quote!(::std::option::Option::Some($binding) =>),
+            body: ast::Block {
+                markups: vec![ast::Markup::Splice { expr: binding.into(), outer_span }],
+                outer_span,
+            },
+        };
+        let none = ast::MatchArm {
+            head: quote!(::std::option::Option::None =>),
+            body: ast::Block { markups: fallback, outer_span: fallback_span },
+        };
+        Ok(ast::Markup::Match {
+            at_span: outer_span,
+            head: quote!(match $value),
+            arms: vec![some, none],
+            arms_span: outer_span,
+        })
+    }
+
+    /// Parses a splice with a fallback for panics, like
+    /// `(value ?? "error")`.
+    fn splice_catch(
+        &mut self,
+        value: TokenStream,
+        fallback: TokenStream,
+        outer_span: Span,
+    ) -> ParseResult<ast::Markup> {
+        let fallback_span = ast::span_tokens(fallback.clone());
+        let fallback = self.with_input(fallback).markups()?;
+        let value_span = ast::span_tokens(value.clone());
+        Ok(ast::Markup::Catch {
+            at_span: outer_span,
+            body: ast::Block {
+                markups: vec![ast::Markup::Splice { expr: format_splice(value), outer_span: value_span }],
+                outer_span: value_span,
+            },
+            fallback: ast::Block { markups: fallback, outer_span: fallback_span },
+        })
+    }
+
     /// Parses an `@if` expression.
     ///
     /// The leading `@if` should already be consumed.
@@ -310,7 +552,8 @@ impl Parser {
     /// The leading `@for` should already be consumed.
     fn for_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
         let keyword_span = keyword.span();
-        let mut head = vec![keyword];
+        let keyword_name = keyword.to_string();
+        let mut head = vec![TokenTree::Ident(Ident::new("for", keyword_span))];
         loop {
             match self.next() {
                 Some(TokenTree::Ident(ref in_keyword)) if in_keyword.to_string() == "in" => {
@@ -320,27 +563,57 @@ impl Parser {
                 Some(token) => head.push(token),
                 None => {
                     let span = at_span.join(keyword_span).unwrap_or(keyword_span);
-                    span.error("missing `in` in `@for` loop").emit();
+                    span.error(format!("missing `in` in `@{}` loop", keyword_name)).emit();
                     return Err(());
                 },
             }
         }
+        let mut iterable = Vec::new();
         let body = loop {
             match self.next() {
                 Some(TokenTree::Group(ref block)) if block.delimiter() == Delimiter::Brace => {
                     break self.block(block.stream(), block.span())?;
                 },
-                Some(token) => head.push(token),
+                Some(token) => iterable.push(token),
                 None => {
                     let span = at_span.join(keyword_span).unwrap_or(keyword_span);
-                    span.error("expected body for this `@for`").emit();
+                    span.error(format!("expected body for this `@{}`", keyword_name)).emit();
                     return Err(());
                 },
             }
         };
-        Ok(ast::Markup::Special {
-            segments: vec![ast::Special { at_span, head: head.into_iter().collect(), body }],
-        })
+        if keyword_name == "for_sorted" {
+            // Collect the pairs and sort them by key first
+            let mut pairs = TokenTree::Group(Group::new(Delimiter::Parenthesis, iterable.iter().cloned().collect()));
+            pairs.set_span(ast::span_tokens(iterable));
+            head.extend(quote!(maud::rt::sorted_by_key($pairs)));
+        } else {
+            head.extend(iterable);
+        }
+        let head = head.into_iter().collect();
+        match self.peek2() {
+            Some((
+                TokenTree::Punct(ref punct),
+                Some(TokenTree::Ident(ref sep_keyword)),
+            )) if punct.as_char() == '@' && is_sep_keyword(&sep_keyword.to_string()) => {
+                self.advance2();
+                let sep = match self.next() {
+                    Some(TokenTree::Group(ref block)) if block.delimiter() == Delimiter::Brace => {
+                        self.block(block.stream(), block.span())?
+                    },
+                    _ => {
+                        let sep_span = sep_keyword.span();
+                        let span = punct.span().join(sep_span).unwrap_or(sep_span);
+                        span.error(format!("expected body for this `@{}`", sep_keyword)).emit();
+                        return Err(());
+                    },
+                };
+                Ok(ast::Markup::Separated { at_span, head, body, sep })
+            },
+            _ => Ok(ast::Markup::Special {
+                segments: vec![ast::Special { at_span, head, body }],
+            }),
+        }
     }
 
     /// Parses a `@match` expression.
@@ -439,6 +712,694 @@ impl Parser {
         Ok(Some(ast::MatchArm { head: head.into_iter().collect(), body }))
     }
 
+    /// Parses a `@break` or `@continue` expression.
+    ///
+    /// The leading `@break` or `@continue` should already be consumed.
+    fn control_expr(&mut self, at_span: Span, keyword: TokenTree) -> ast::Markup {
+        let semi = match self.peek() {
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == ';' => {
+                self.advance();
+                punct.clone()
+            },
+            _ => Punct::new(';', Spacing::Alone),
+        };
+        let tokens = vec![keyword, TokenTree::Punct(semi)];
+        ast::Markup::Control { at_span, tokens: tokens.into_iter().collect() }
+    }
+
+    /// Parses a `@do expr;` statement, which runs `expr` for its side
+    /// effects.
+    ///
+    /// The leading `@do` should already be consumed.
+    fn do_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let mut tokens = Vec::new();
+        loop {
+            match self.next() {
+                Some(TokenTree::Punct(ref punct)) if punct.as_char() == ';' => {
+                    tokens.push(TokenTree::Punct(punct.clone()));
+                    break;
+                },
+                Some(token) => tokens.push(token),
+                None => {
+                    let span = at_span.join(keyword.span()).unwrap_or(at_span);
+                    let tokens_span = ast::span_tokens(tokens);
+                    let span = span.join(tokens_span).unwrap_or(span);
+                    span.error("unexpected end of `@do` statement (are you missing a semicolon?)").emit();
+                    return Err(());
+                },
+            }
+        }
+        if tokens.len() == 1 {
+            let span = at_span.join(keyword.span()).unwrap_or(at_span);
+            span.error("expected an expression after `@do`").emit();
+            return Err(());
+        }
+        Ok(ast::Markup::Control { at_span, tokens: tokens.into_iter().collect() })
+    }
+
+    /// Parses an `@env "NAME"` node, which splices in an environment
+    /// variable at compile time.
+    ///
+    /// The leading `@env` should already be consumed.
+    fn env_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        match self.next() {
+            Some(TokenTree::Literal(name)) if name.to_string().starts_with('"') => {
+                let span = at_span.join(name.span()).unwrap_or(at_span);
+                let name = TokenTree::Literal(name);
+                // Point `env!` errors at the `@env` node
+                let expr = quote!(::std::env!($name))
+                    .into_iter()
+                    .map(|mut token| {
+                        token.set_span(span);
+                        token
+                    })
+                    .collect();
+                Ok(ast::Markup::Splice { expr, outer_span: span })
+            },
+            _ => {
+                let span = at_span.join(keyword.span()).unwrap_or(at_span);
+                span.error("expected a variable name like `@env \"CARGO_PKG_VERSION\"`").emit();
+                Err(())
+            },
+        }
+    }
+
+    /// Parses a `@text (expr)` splice, which always escapes `expr`.
+    ///
+    /// The leading `@text` should already be consumed.
+    fn text_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        match self.next() {
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => {
+                // `display()` only takes `Display` types, so `PreEscaped` and
+                // other markup can't pass through unescaped
+                let mut expr = TokenTree::Group(Group::new(Delimiter::None, group.stream()));
+                expr.set_span(group.span());
+                let outer_span = at_span.join(group.span()).unwrap_or(at_span);
+                // Point type errors at the `@text` node
+                let display = quote!(maud::display)
+                    .into_iter()
+                    .map(|mut token| {
+                        token.set_span(outer_span);
+                        token
+                    })
+                    .collect::<TokenStream>();
+                Ok(ast::Markup::Splice { expr: quote!($display(&$expr)), outer_span })
+            },
+            _ => {
+                let span = at_span.join(keyword.span()).unwrap_or(at_span);
+                span.error("expected an expression in parentheses, as in `@text (comment.body)`").emit();
+                Err(())
+            },
+        }
+    }
+
+    /// Parses a `@truncate limit (expr)` splice, which writes at most
+    /// `limit` characters of `expr`.
+    ///
+    /// The leading `@truncate` should already be consumed.
+    fn truncate_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        let limit = match self.next() {
+            Some(TokenTree::Literal(limit)) => match limit.to_string().parse::<usize>() {
+                Ok(limit) => limit,
+                Err(_) => {
+                    limit.span().error("expected a length like `50`").emit();
+                    return Err(());
+                },
+            },
+            _ => {
+                keyword_span.error("expected a length and an expression, as in `@truncate 50 (post.body)`").emit();
+                return Err(());
+            },
+        };
+        match self.next() {
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => {
+                let mut expr = TokenTree::Group(Group::new(Delimiter::None, group.stream()));
+                expr.set_span(group.span());
+                let outer_span = at_span.join(group.span()).unwrap_or(at_span);
+                let limit = TokenTree::Literal(Literal::usize_unsuffixed(limit));
+                // Point type errors at the `@truncate` node
+                let truncated = quote!(maud::rt::Truncated)
+                    .into_iter()
+                    .map(|mut token| {
+                        token.set_span(outer_span);
+                        token
+                    })
+                    .collect::<TokenStream>();
+                Ok(ast::Markup::Splice { expr: quote!($truncated(&$expr, $limit)), outer_span })
+            },
+            _ => {
+                keyword_span.error("expected a length and an expression, as in `@truncate 50 (post.body)`").emit();
+                Err(())
+            },
+        }
+    }
+
+    /// Parses a `@write |writer| { ... }` block.
+    ///
+    /// The leading `@write` should already be consumed.
+    fn write_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        let is_bar = |token: &Option<TokenTree>| matches!(*token, Some(TokenTree::Punct(ref punct)) if punct.as_char() == '|');
+        let (open, writer, close, body) = (self.next(), self.next(), self.next(), self.next());
+        match (writer, body) {
+            (Some(TokenTree::Ident(writer)), Some(body @ TokenTree::Group(_)))
+            if is_bar(&open) && is_bar(&close)
+                && matches!(body, TokenTree::Group(ref group) if group.delimiter() == Delimiter::Brace) => {
+                Ok(ast::Markup::Write { at_span, writer, body })
+            },
+            _ => {
+                keyword_span.error("expected a writer and a block, as in `@write |w| { ... }`").emit();
+                Err(())
+            },
+        }
+    }
+
+    /// Parses `@dl from pairs;`, which writes a `dt` and `dd` for each
+    /// key-value pair.
+    ///
+    /// The leading `@dl` should already be consumed.
+    fn dl_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        match self.next() {
+            Some(TokenTree::Ident(ref from)) if from.to_string() == "from" => {},
+            _ => {
+                keyword_span.error("expected `from` after `@dl`, as in `@dl from pairs;`").emit();
+                return Err(());
+            },
+        }
+        let mut pairs = Vec::new();
+        loop {
+            match self.next() {
+                Some(TokenTree::Punct(ref punct)) if punct.as_char() == ';' => break,
+                Some(token) => pairs.push(token),
+                None => break,
+            }
+        }
+        if pairs.is_empty() {
+            keyword_span.error("expected the pairs to list after `@dl from`").emit();
+            return Err(());
+        }
+        let pairs_span = ast::span_tokens(pairs.clone());
+        let mut pairs = TokenTree::Group(Group::new(Delimiter::Parenthesis, pairs.into_iter().collect()));
+        pairs.set_span(pairs_span);
+        let key = TokenTree::Ident(Ident::new("__maud_key", Span::def_site()));
+        let value = TokenTree::Ident(Ident::new("__maud_value", Span::def_site()));
+        let item = |name: &str, expr: &TokenTree| ast::Markup::Element {
+            name: TokenTree::Ident(Ident::new(name, keyword_span)).into(),
+            attrs: Vec::new(),
+            body: ast::ElementBody::Block {
+                block: ast::Block {
+                    markups: vec![ast::Markup::Splice { expr: expr.clone().into(), outer_span: pairs_span }],
+                    outer_span: pairs_span,
+                },
+            },
+        };
+        let body = ast::Block {
+            markups: vec![item("dt", &key), item("dd", &value)],
+            outer_span: pairs_span,
+        };
+        Ok(ast::Markup::Special {
+            segments: vec![ast::Special {
+                at_span,
+                head: quote!(for ($key, $value) in $pairs),
+                body,
+            }],
+        })
+    }
+
+    /// Parses a `@table headers=(...) rows=(...)` table.
+    ///
+    /// The leading `@table` should already be consumed.
+    fn table_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        let example = "@table headers=(headers) rows=(rows)";
+        let headers = self.named_arg(keyword_span, "headers", example)?;
+        let rows = self.named_arg(keyword_span, "rows", example)?;
+        let headers_span = headers.span();
+        let rows_span = rows.span();
+        let headers_ident = TokenTree::Ident(Ident::new("__maud_headers", Span::def_site()));
+        let header = TokenTree::Ident(Ident::new("__maud_header", Span::def_site()));
+        let row = TokenTree::Ident(Ident::new("__maud_row", Span::def_site()));
+        let cell = TokenTree::Ident(Ident::new("__maud_cell", Span::def_site()));
+        let element = |name: &str, markups: Vec<ast::Markup>, outer_span: Span| ast::Markup::Element {
+            name: TokenTree::Ident(Ident::new(name, keyword_span)).into(),
+            attrs: Vec::new(),
+            body: ast::ElementBody::Block { block: ast::Block { markups, outer_span } },
+        };
+        let for_each = |head: TokenStream, body: ast::Markup, outer_span: Span| ast::Markup::Special {
+            segments: vec![ast::Special {
+                at_span,
+                head,
+                body: ast::Block { markups: vec![body], outer_span },
+            }],
+        };
+        let splice = |expr: &TokenTree, outer_span: Span| ast::Markup::Splice {
+            expr: expr.clone().into(),
+            outer_span,
+        };
+        let thead = element("thead", vec![element("tr", vec![for_each(
+            quote!(for $header in $headers_ident.iter()),
+            element("th", vec![splice(&header, headers_span)], headers_span),
+            headers_span,
+        )], headers_span)], headers_span);
+        // Rows with fewer cells than headers are padded with empty cells
+        let tbody = element("tbody", vec![for_each(
+            quote!(for $row in $rows.iter()),
+            element("tr", vec![
+                for_each(
+                    quote!(for $cell in $row.iter()),
+                    element("td", vec![splice(&cell, rows_span)], rows_span),
+                    rows_span,
+                ),
+                for_each(
+                    quote!(for _ in $row.len()..$headers_ident.len()),
+                    element("td", Vec::new(), rows_span),
+                    rows_span,
+                ),
+            ], rows_span),
+            rows_span,
+        )], rows_span);
+        Ok(ast::Markup::Block(ast::Block {
+            markups: vec![
+                ast::Markup::Let { at_span, tokens: quote!(let $headers_ident = &$headers;) },
+                element("table", vec![thead, tbody], keyword_span),
+            ],
+            outer_span: keyword_span,
+        }))
+    }
+
+    /// Parses an `@options (items) selected=(current)` list, and lowers it
+    /// to a loop over `option` elements.
+    ///
+    /// The leading `@options` should already be consumed.
+    fn options_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        if self.in_attr {
+            keyword_span.error("`@options` can't be used in an attribute").emit();
+            return Err(());
+        }
+        let items = match self.next() {
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => group.clone(),
+            _ => {
+                keyword_span.error("expected a list of `(value, label)` pairs, as in `@options (items)`").emit();
+                return Err(());
+            },
+        };
+        let selected = match self.peek() {
+            Some(TokenTree::Ident(ref ident)) if ident.to_string() == "selected" => {
+                Some(self.named_arg(keyword_span, "selected", "@options (items) selected=(current)")?)
+            },
+            _ => None,
+        };
+        let items_span = items.span();
+        let option = TokenTree::Ident(Ident::new("__maud_option", Span::def_site()));
+        let selected_ident = TokenTree::Ident(Ident::new("__maud_selected", Span::def_site()));
+        let splice = |expr: TokenStream| ast::Markup::Splice { expr, outer_span: items_span };
+        let mut attrs = vec![ast::Attr::Attribute {
+            attribute: ast::Attribute {
+                name: TokenTree::Ident(Ident::new("value", keyword_span)).into(),
+                attr_type: ast::AttrType::Normal { value: splice(quote!($option.0)) },
+                cfg: None,
+            },
+        }];
+        if let Some(ref selected) = selected {
+            attrs.push(ast::Attr::Attribute {
+                attribute: ast::Attribute {
+                    name: TokenTree::Ident(Ident::new("selected", keyword_span)).into(),
+                    attr_type: ast::AttrType::Empty {
+                        toggler: Some(ast::Toggler {
+                            cond: quote!($option.0 == *$selected_ident),
+                            cond_span: selected.span(),
+                        }),
+                    },
+                    cfg: None,
+                },
+            });
+        }
+        let element = ast::Markup::Element {
+            name: TokenTree::Ident(Ident::new("option", keyword_span)).into(),
+            attrs,
+            body: ast::ElementBody::Block {
+                block: ast::Block { markups: vec![splice(quote!($option.1))], outer_span: items_span },
+            },
+        };
+        let mut markups = Vec::new();
+        if let Some(selected) = selected {
+            markups.push(ast::Markup::Let { at_span, tokens: quote!(let $selected_ident = &$selected;) });
+        }
+        markups.push(ast::Markup::Special {
+            segments: vec![ast::Special {
+                at_span,
+                head: quote!(for $option in $items.iter()),
+                body: ast::Block { markups: vec![element], outer_span: items_span },
+            }],
+        });
+        Ok(ast::Markup::Block(ast::Block { markups, outer_span: keyword_span }))
+    }
+
+    /// Parses a `name=(...)` argument, like those of `@table`. `example`
+    /// shows how the whole form is written.
+    fn named_arg(&mut self, keyword_span: Span, name: &str, example: &str) -> ParseResult<TokenTree> {
+        match (self.next(), self.next(), self.next()) {
+            (
+                Some(TokenTree::Ident(ref ident)),
+                Some(TokenTree::Punct(ref eq)),
+                Some(TokenTree::Group(ref group)),
+            ) if ident.to_string() == name && eq.as_char() == '=' && group.delimiter() == Delimiter::Parenthesis => {
+                Ok(TokenTree::Group(group.clone()))
+            },
+            _ => {
+                keyword_span.error(format!("expected `{}=(...)`, as in `{}`", name, example)).emit();
+                Err(())
+            },
+        }
+    }
+
+    /// Parses a `@json_script attrs... (value)`, which embeds `value` as
+    /// JSON in a `script` element.
+    ///
+    /// The leading `@json_script` should already be consumed.
+    fn json_script_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        if self.in_attr {
+            keyword_span.error("`@json_script` can't be used in an attribute").emit();
+            return Err(());
+        }
+        let attrs = self.attrs()?;
+        for attr in &attrs {
+            if let ast::Attr::Attribute { attribute } = attr {
+                if ast::name_string(&attribute.name).eq_ignore_ascii_case("type") {
+                    attribute.span().error("`@json_script` sets the `type` itself").emit();
+                    return Err(());
+                }
+            }
+        }
+        let value = match self.next() {
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => group.clone(),
+            _ => {
+                keyword_span.error("expected a value after `@json_script`, as in `@json_script id=\"data\" (value)`").emit();
+                return Err(());
+            },
+        };
+        let mut expr = TokenTree::Group(Group::new(Delimiter::None, value.stream()));
+        expr.set_span(value.span());
+        let type_attr = ast::Attr::Attribute {
+            attribute: ast::Attribute {
+                name: TokenTree::Ident(Ident::new("type", keyword_span)).into(),
+                attr_type: ast::AttrType::Normal {
+                    value: ast::Markup::Literal { content: "application/json".to_string(), span: keyword_span },
+                },
+                cfg: None,
+            },
+        };
+        Ok(ast::Markup::Element {
+            name: TokenTree::Ident(Ident::new("script", keyword_span)).into(),
+            attrs: std::iter::once(type_attr).chain(attrs).collect(),
+            body: ast::ElementBody::Block {
+                block: ast::Block {
+                    markups: vec![ast::Markup::Splice { expr: quote!(maud::Json(&$expr)), outer_span: value.span() }],
+                    outer_span: value.span(),
+                },
+            },
+        })
+    }
+
+    /// Parses a `@partial "path"`, and the template in the file it names.
+    ///
+    /// The leading `@partial` should already be consumed.
+    fn partial_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        if self.in_attr {
+            keyword_span.error("`@partial` can't be used in an attribute").emit();
+            return Err(());
+        }
+        let lit = match self.next() {
+            Some(TokenTree::Literal(lit)) => lit,
+            _ => {
+                keyword_span.error("expected the path to a template file, like `@partial \"header.maud\"`").emit();
+                return Err(());
+            },
+        };
+        let path = match parse_str::<LitStr>(&lit.to_string()) {
+            Ok(path) => path.value(),
+            Err(_) => {
+                lit.span().error("expected a string literal").emit();
+                return Err(());
+            },
+        };
+        // Paths in a partial are relative to the partial itself
+        let path = match self.partials.last().and_then(|(parent, _)| parent.parent()) {
+            Some(dir) => dir.join(path),
+            None => crate::resolve_include(&path),
+        };
+        // Compare real paths, so that `../partials/x.maud` inside `x.maud`
+        // is caught too. If the file doesn't exist, reading it fails below
+        let real_path = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if self.partials.iter().any(|(_, other)| *other == real_path) {
+            lit.span().error(format!("`{}` includes itself", path.display())).emit();
+            return Err(());
+        }
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                lit.span().error(format!("couldn't read `{}`: {}", path.display(), e)).emit();
+                return Err(());
+            },
+        };
+        let input = match source.parse::<TokenStream>() {
+            Ok(input) => input.into_iter().collect::<Rc<[TokenTree]>>(),
+            Err(e) => {
+                lit.span().error(format!("couldn't parse `{}`: {}", path.display(), e)).emit();
+                return Err(());
+            },
+        };
+        // Rebuild when the partial changes
+        proc_macro::tracked::path(&path);
+        let file = Rc::new(SourceFile::new(source));
+        let mut parser = Parser {
+            in_attr: false,
+            source: file.offsets(&input).map(|offsets| (file.clone(), offsets)),
+            input,
+            pos: 0,
+            partials: self.partials.iter().cloned().chain(Some((path.clone(), real_path))).collect(),
+            inline_html: self.inline_html,
+        };
+        let markups = parser.markups().map_err(|()| {
+            for location in file_locations(&path, &file) {
+                lit.span().note(format!("in partial `{}`", location)).emit();
+            }
+        })?;
+        Ok(ast::Markup::Block(ast::Block {
+            markups,
+            outer_span: at_span.join(lit.span()).unwrap_or(at_span),
+        }))
+    }
+
+    /// Parses a `@pi "..."` processing instruction.
+    ///
+    /// The leading `@pi` should already be consumed.
+    fn pi_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        let lit = match self.next() {
+            Some(TokenTree::Literal(lit)) => lit,
+            _ => {
+                keyword_span.error("expected a string after `@pi`").emit();
+                return Err(());
+            },
+        };
+        let content = match self.literal(&lit)? {
+            ast::Markup::Literal { content, .. } => content,
+            _ => unreachable!("literal() always returns a literal"),
+        };
+        if !content.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            lit.span().error("a processing instruction must start with its target, like `xml`").emit();
+            return Err(());
+        }
+        if content.contains("?>") {
+            lit.span().error("a processing instruction can't contain `?>`").emit();
+            return Err(());
+        }
+        Ok(ast::Markup::ProcessingInstruction { at_span, content, content_span: lit.span() })
+    }
+
+    /// Parses an `@ie_comment "..." { ... }` conditional comment.
+    ///
+    /// The leading `@ie_comment` should already be consumed.
+    fn ie_comment_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        let lit = match self.next() {
+            Some(TokenTree::Literal(lit)) => lit,
+            _ => {
+                keyword_span.error("expected a condition after `@ie_comment`, like `\"lt IE 9\"`").emit();
+                return Err(());
+            },
+        };
+        let condition = match self.literal(&lit)? {
+            ast::Markup::Literal { content, .. } => content,
+            _ => unreachable!("literal() always returns a literal"),
+        };
+        if condition.trim().is_empty() || condition.contains(']') || condition.contains("--") {
+            lit.span().error("expected a condition like `\"lt IE 9\"`").emit();
+            return Err(());
+        }
+        let body = match self.next() {
+            Some(TokenTree::Group(ref block)) if block.delimiter() == Delimiter::Brace => {
+                self.block(block.stream(), block.span())?
+            },
+            _ => {
+                keyword_span.error("expected body for this `@ie_comment`").emit();
+                return Err(());
+            },
+        };
+        Ok(ast::Markup::ConditionalComment { at_span, condition, body })
+    }
+
+    /// Parses a `@cfg` block.
+    ///
+    /// The leading `@cfg` should already be consumed.
+    fn cfg_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let cfg = self.cfg_pred(at_span, keyword)?;
+        let body = match self.next() {
+            Some(TokenTree::Group(ref block)) if block.delimiter() == Delimiter::Brace => {
+                self.block(block.stream(), block.span())?
+            },
+            _ => {
+                cfg.span().error("expected body for this `@cfg`").emit();
+                return Err(());
+            },
+        };
+        Ok(ast::Markup::Cfg { cfg, body })
+    }
+
+    /// Parses the `(predicate)` part of a `@cfg`.
+    ///
+    /// The leading `@cfg` should already be consumed.
+    fn cfg_pred(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Cfg> {
+        match self.next() {
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => {
+                Ok(ast::Cfg { at_span, pred: TokenTree::Group(group.clone()) })
+            },
+            _ => {
+                let keyword_span = keyword.span();
+                let span = at_span.join(keyword_span).unwrap_or(keyword_span);
+                span.error("expected a predicate after `@cfg`, like `@cfg(debug_assertions)`")
+                    .emit();
+                Err(())
+            },
+        }
+    }
+
+    /// Parses an `@extends` expression.
+    ///
+    /// The leading `@extends` should already be consumed.
+    fn extends_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = keyword.span();
+        let mut layout = Vec::new();
+        let (blocks, blocks_span) = loop {
+            match self.next() {
+                Some(TokenTree::Group(ref body)) if body.delimiter() == Delimiter::Brace => {
+                    break (self.with_input(body.stream()).named_blocks()?, body.span());
+                },
+                Some(token) => layout.push(token),
+                None => {
+                    let span = at_span.join(keyword_span).unwrap_or(keyword_span);
+                    span.error("expected body for this `@extends`").emit();
+                    return Err(());
+                },
+            }
+        };
+        if layout.is_empty() {
+            let span = at_span.join(keyword_span).unwrap_or(keyword_span);
+            span.error("expected a layout after `@extends`").emit();
+            return Err(());
+        }
+        Ok(ast::Markup::Extends {
+            at_span,
+            layout: layout.into_iter().collect(),
+            blocks,
+            blocks_span,
+        })
+    }
+
+    /// Parses an `@around` expression.
+    ///
+    /// The leading `@around` should already be consumed.
+    fn around_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let keyword_span = at_span.join(keyword.span()).unwrap_or(at_span);
+        let mut wrapper = Vec::new();
+        let body = loop {
+            match self.next() {
+                Some(TokenTree::Group(ref body)) if body.delimiter() == Delimiter::Brace => {
+                    break self.block(body.stream(), body.span())?;
+                },
+                Some(token) => wrapper.push(token),
+                None => {
+                    keyword_span.error("expected body for this `@around`").emit();
+                    return Err(());
+                },
+            }
+        };
+        if wrapper.is_empty() {
+            keyword_span.error("expected a function after `@around`, as in `@around card { ... }`").emit();
+            return Err(());
+        }
+        Ok(ast::Markup::Around { at_span, wrapper: wrapper.into_iter().collect(), body })
+    }
+
+    /// Parses a `@spaceless` block.
+    ///
+    /// The leading `@spaceless` should already be consumed.
+    fn spaceless_expr(&mut self, at_span: Span, keyword: TokenTree) -> ParseResult<ast::Markup> {
+        let mut block = match self.next() {
+            Some(TokenTree::Group(ref block)) if block.delimiter() == Delimiter::Brace => {
+                self.block(block.stream(), block.span())?
+            },
+            _ => {
+                let span = at_span.join(keyword.span()).unwrap_or(at_span);
+                span.error("expected body for this `@spaceless`").emit();
+                return Err(());
+            },
+        };
+        strip_spaces_between_tags(&mut block.markups, true);
+        Ok(ast::Markup::Block(block))
+    }
+
+    /// Parses the `@block`s in the body of an `@extends`.
+    fn named_blocks(&mut self) -> ParseResult<Vec<ast::NamedBlock>> {
+        let mut blocks = Vec::new();
+        loop {
+            match self.next() {
+                None => break,
+                Some(TokenTree::Punct(ref punct)) if punct.as_char() == '@' => {
+                    let at_span = punct.span();
+                    match (self.next(), self.next(), self.next()) {
+                        (
+                            Some(TokenTree::Ident(ref keyword)),
+                            Some(name @ TokenTree::Ident(_)),
+                            Some(TokenTree::Group(ref body)),
+                        ) if keyword.to_string() == "block" && body.delimiter() == Delimiter::Brace => {
+                            let body = self.block(body.stream(), body.span())?;
+                            blocks.push(ast::NamedBlock { name, body });
+                        },
+                        _ => {
+                            at_span.error("expected `@block name { ... }`").emit();
+                            return Err(());
+                        },
+                    }
+                },
+                Some(token) => {
+                    token.span()
+                        .error("expected `@block`")
+                        .help("only `@block`s are allowed directly inside `@extends`")
+                        .emit();
+                    return Err(());
+                },
+            }
+        }
+        Ok(blocks)
+    }
+
     /// Parses a `@let` expression.
     ///
     /// The leading `@let` should already be consumed.
@@ -528,25 +1489,36 @@ impl Parser {
     /// Parses the attributes of an element.
     fn attrs(&mut self) -> ParseResult<ast::Attrs> {
         let mut attrs = Vec::new();
+        let mut cfg = None;
         loop {
             let mut attempt = self.clone();
-            let maybe_name = attempt.try_namespaced_name();
+            let maybe_name = attempt.try_namespaced_name().or_else(|| attempt.try_quoted_name());
             let token_after = attempt.next();
             match (maybe_name, token_after) {
                 // Non-empty attribute
                 (Some(ref name), Some(TokenTree::Punct(ref punct))) if punct.as_char() == '=' => {
                     self.commit(attempt);
-                    let value;
-                    {
-                        // Parse a value under an attribute context
-                        let in_attr = mem::replace(&mut self.in_attr, true);
-                        value = self.markup()?;
-                        self.in_attr = in_attr;
-                    }
+                    let attr_type = match self.peek2() {
+                        Some((
+                            TokenTree::Ident(ref keyword),
+                            Some(TokenTree::Group(ref group)),
+                        )) if keyword.to_string() == "toggle" && group.delimiter() == Delimiter::Bracket => {
+                            self.advance2();
+                            self.class_flags(name, keyword, group)?
+                        },
+                        _ => {
+                            // Parse a value under an attribute context
+                            let in_attr = mem::replace(&mut self.in_attr, true);
+                            let result = self.markup();
+                            self.in_attr = in_attr;
+                            ast::AttrType::Normal { value: result? }
+                        },
+                    };
                     attrs.push(ast::Attr::Attribute {
                         attribute: ast::Attribute {
                             name: name.clone(),
-                            attr_type: ast::AttrType::Normal { value },
+                            attr_type,
+                            cfg: cfg.take(),
                         },
                     });
                 },
@@ -558,9 +1530,49 @@ impl Parser {
                         attribute: ast::Attribute {
                             name: name.clone(),
                             attr_type: ast::AttrType::Empty { toggler },
+                            cfg: cfg.take(),
+                        },
+                    });
+                },
+                // Toggled empty attribute without the `?`, as in `selected[cond]`
+                (Some(ref name), Some(TokenTree::Group(ref group)))
+                if group.delimiter() == Delimiter::Bracket => {
+                    self.commit(attempt);
+                    attrs.push(ast::Attr::Attribute {
+                        attribute: ast::Attribute {
+                            name: name.clone(),
+                            attr_type: ast::AttrType::Empty {
+                                toggler: Some(ast::Toggler {
+                                    cond: group.stream(),
+                                    cond_span: group.span(),
+                                }),
+                            },
+                            cfg: cfg.take(),
                         },
                     });
                 },
+                // Conditionally compiled attribute
+                (None, Some(TokenTree::Punct(ref punct)))
+                if punct.as_char() == '@' && cfg.is_none() && attempt.at_cfg_attr() => {
+                    let keyword = attempt.next().expect("keyword");
+                    self.commit(attempt);
+                    cfg = Some(self.cfg_pred(punct.span(), keyword)?);
+                },
+                // Default attributes
+                (None, Some(TokenTree::Punct(ref punct)))
+                if punct.as_char() == '@' && cfg.is_none() && attempt.at_defaults_attr() => {
+                    attempt.next();
+                    let group = match attempt.next() {
+                        Some(TokenTree::Group(group)) => group,
+                        _ => unreachable!("checked by `at_defaults_attr`"),
+                    };
+                    self.commit(attempt);
+                    attrs.push(ast::Attr::Defaults {
+                        at_span: punct.span(),
+                        pairs: group.stream(),
+                        pairs_span: group.span(),
+                    });
+                },
                 // Class shorthand
                 (None, Some(TokenTree::Punct(ref punct))) if punct.as_char() == '.' => {
                     self.commit(attempt);
@@ -578,6 +1590,23 @@ impl Parser {
                 _ => break,
             }
         }
+        if let Some(cfg) = cfg {
+            cfg.span().error("expected an attribute after this `@cfg`").emit();
+            return Err(());
+        }
+
+        // Quoted names can hold anything, so check they can't break out of
+        // the tag
+        for attr in &attrs {
+            if let ast::Attr::Attribute { attribute } = attr {
+                let name = ast::name_string(&attribute.name);
+                let invalid = |c: char| c.is_whitespace() || c.is_control() || "\"'<>/=".contains(c);
+                if name.is_empty() || name.contains(invalid) {
+                    let span = ast::span_tokens(attribute.name.clone());
+                    span.error(format!("invalid attribute name `{}`", name)).emit();
+                }
+            }
+        }
 
         let mut attr_map: HashMap<String, Vec<Span>> = HashMap::new();
         let mut has_class = false;
@@ -592,9 +1621,11 @@ impl Parser {
                     "class".to_string()
                 },
                 ast::Attr::Id { .. } => "id".to_string(),
-                ast::Attr::Attribute { attribute } => {
-                    attribute.name.clone().into_iter().map(|token| token.to_string()).collect()
-                },
+                // Attributes under `@cfg` may be duplicated, as long as
+                // at most one of them is compiled in
+                ast::Attr::Attribute { attribute } if attribute.cfg.is_some() => continue,
+                ast::Attr::Attribute { attribute } => ast::name_string(&attribute.name),
+                ast::Attr::Defaults { .. } => continue,
             };
             let entry = attr_map.entry(name).or_default();
             entry.push(attr.span());
@@ -616,6 +1647,31 @@ impl Parser {
         Ok(attrs)
     }
 
+    /// Checks whether the upcoming tokens are the `cfg(...)` of a
+    /// conditional attribute, as opposed to a `@cfg` block in the body.
+    fn at_cfg_attr(&mut self) -> bool {
+        let mut attempt = self.clone();
+        match (attempt.next(), attempt.next(), attempt.next()) {
+            (
+                Some(TokenTree::Ident(ref keyword)),
+                Some(TokenTree::Group(ref group)),
+                Some(TokenTree::Ident(_)),
+            ) => keyword.to_string() == "cfg" && group.delimiter() == Delimiter::Parenthesis,
+            _ => false,
+        }
+    }
+
+    /// Checks whether the upcoming tokens are the `defaults(...)` of a
+    /// `@defaults` attribute.
+    fn at_defaults_attr(&mut self) -> bool {
+        match self.peek2() {
+            Some((TokenTree::Ident(ref keyword), Some(TokenTree::Group(ref group)))) => {
+                keyword.to_string() == "defaults" && group.delimiter() == Delimiter::Parenthesis
+            },
+            _ => false,
+        }
+    }
+
     /// Parses the name of a class or ID.
     fn class_or_id_name(&mut self) -> ParseResult<ast::Markup> {
         if let Some(symbol) = self.try_name() {
@@ -639,6 +1695,21 @@ impl Parser {
         }
     }
 
+    /// Parses the `[flags]` in `class=toggle[flags]`.
+    fn class_flags(
+        &mut self,
+        name: &TokenStream,
+        keyword: &Ident,
+        group: &Group,
+    ) -> ParseResult<ast::AttrType> {
+        let span = keyword.span().join(group.span()).unwrap_or_else(|| keyword.span());
+        if name.to_string() != "class" {
+            span.error("`toggle[...]` only works on the `class` attribute").emit();
+            return Err(());
+        }
+        Ok(ast::AttrType::ClassFlags { flags: group.stream(), span })
+    }
+
     /// Parses an identifier, without dealing with namespaces.
     fn try_name(&mut self) -> Option<TokenStream> {
         let mut result = Vec::new();
@@ -681,9 +1752,319 @@ impl Parser {
         Some(result.into_iter().collect())
     }
 
+    /// Parses a quoted attribute name, like `"@click"`.
+    fn try_quoted_name(&mut self) -> Option<TokenStream> {
+        match self.peek() {
+            Some(TokenTree::Literal(ref lit)) if lit.to_string().starts_with('"') => {
+                self.advance();
+                Some(TokenStream::from(TokenTree::Literal(lit.clone())))
+            },
+            _ => None,
+        }
+    }
+
     /// Parses the given token stream as a Maud expression.
     fn block(&mut self, body: TokenStream, outer_span: Span) -> ParseResult<ast::Block> {
         let markups = self.with_input(body).markups()?;
         Ok(ast::Block { markups, outer_span })
     }
 }
+
+enum Suggestion {
+    /// A closing tag like `</div>`.
+    Close,
+    /// The Maud syntax for an opening tag.
+    Open(String),
+}
+
+/// Translates the inside of a simple HTML tag, such as `br/` or
+/// `a href="/"`, into Maud syntax.
+fn suggest_element(tokens: &[TokenTree]) -> Option<Suggestion> {
+    let mut tokens = tokens.iter().peekable();
+    if let Some(TokenTree::Punct(ref punct)) = tokens.peek() {
+        if punct.as_char() == '/' {
+            return Some(Suggestion::Close);
+        }
+    }
+    let name = html_name(&mut tokens)?;
+    let mut result = name.clone();
+    let mut self_closing = false;
+    loop {
+        match tokens.peek() {
+            None => break,
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == '/' => {
+                tokens.next();
+                self_closing = true;
+                if tokens.peek().is_some() {
+                    return None;
+                }
+            },
+            Some(_) => {
+                let attr = html_name(&mut tokens)?;
+                result.push(' ');
+                result.push_str(&attr);
+                match tokens.peek() {
+                    Some(TokenTree::Punct(ref punct)) if punct.as_char() == '=' => {
+                        tokens.next();
+                        match tokens.next() {
+                            Some(TokenTree::Literal(ref lit)) if lit.to_string().starts_with('"') => {
+                                result.push('=');
+                                result.push_str(&lit.to_string());
+                            },
+                            _ => return None,
+                        }
+                    },
+                    _ => result.push('?'),
+                }
+            },
+        }
+    }
+    if self_closing || is_void_element(&name) {
+        result.push(';');
+    } else {
+        result.push_str(" { ... }");
+    }
+    Some(Suggestion::Open(result))
+}
+
+/// Reads a hyphenated name like `data-id`.
+fn html_name<'a, I: Iterator<Item=&'a TokenTree>>(tokens: &mut Peekable<I>) -> Option<String> {
+    let mut name = match tokens.next() {
+        Some(TokenTree::Ident(ref ident)) => ident.to_string(),
+        _ => return None,
+    };
+    while let Some(TokenTree::Punct(ref punct)) = tokens.peek() {
+        if punct.as_char() != '-' {
+            break;
+        }
+        tokens.next();
+        match tokens.next() {
+            Some(TokenTree::Ident(ref ident)) => {
+                name.push('-');
+                name.push_str(&ident.to_string());
+            },
+            _ => return None,
+        }
+    }
+    Some(name)
+}
+
+/// `@separated_by` is a longer spelling of `@sep`.
+fn is_sep_keyword(name: &str) -> bool {
+    name == "sep" || name == "separated_by"
+}
+
+fn is_void_element(name: &str) -> bool {
+    matches!(
+        name,
+        "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input" | "link" | "meta"
+        | "source" | "track" | "wbr"
+    )
+}
+
+/// Elements whose whitespace is kept as is, as in `pretty.rs`.
+fn is_preformatted_element(name: &str) -> bool {
+    matches!(name, "pre" | "textarea" | "script" | "style")
+}
+
+/// Rewrites a splice with a format spec, like `(value:>8.2)`, to use
+/// `format_args!`. `(value#)` is short for `(value:#)`.
+///
+/// A `:` only starts a spec if what follows is a valid one, so closures
+/// like `(|x: u8| x)` are left alone.
+fn format_splice(expr: TokenStream) -> TokenStream {
+    let tokens = expr.clone().into_iter().collect::<Vec<_>>();
+    let colon = tokens.iter().enumerate().position(|(i, token)| match *token {
+        TokenTree::Punct(ref punct) => {
+            // Skip both halves of `::`
+            let is_colon = |token: Option<&TokenTree>| matches!(token, Some(TokenTree::Punct(ref punct)) if punct.as_char() == ':');
+            let path = (punct.spacing() == Spacing::Joint && is_colon(tokens.get(i + 1)))
+                || (i > 0 && is_colon(tokens.get(i - 1)));
+            punct.as_char() == ':' && !path && i > 0
+        },
+        _ => false,
+    });
+    if let Some(colon) = colon {
+        let spec = tokens[colon + 1..].iter().map(|token| token.to_string()).collect::<String>();
+        if is_format_spec(&spec) {
+            return format_args(&spec, &tokens[..colon], &tokens[colon..]);
+        }
+    }
+    // A trailing `#` can't be part of an expression
+    match *tokens.as_slice() {
+        [ref value @ .., TokenTree::Punct(ref hash)] if hash.as_char() == '#' && !value.is_empty() => {
+            format_args("#", value, &tokens[value.len()..])
+        },
+        _ => expr,
+    }
+}
+
+/// Builds a `format_args!` call that formats `value` with `spec`, which
+/// was written as `spec_tokens`.
+fn format_args(spec: &str, value: &[TokenTree], spec_tokens: &[TokenTree]) -> TokenStream {
+    let mut format = Literal::string(&format!("{{:{}}}", spec));
+    format.set_span(ast::span_tokens(spec_tokens.to_vec()));
+    let mut value_group = Group::new(Delimiter::Parenthesis, value.iter().cloned().collect());
+    value_group.set_span(ast::span_tokens(value.to_vec()));
+    let (format, value) = (TokenTree::Literal(format), TokenTree::Group(value_group));
+    quote!(::std::format_args!($format, $value))
+}
+
+/// Checks a `format!` spec like `>8.2`, without named or positional
+/// arguments.
+fn is_format_spec(spec: &str) -> bool {
+    let mut rest = spec;
+    let is_align = |c: Option<char>| matches!(c, Some('<') | Some('^') | Some('>'));
+    if is_align(rest.chars().nth(1)) {
+        rest = &rest[rest.chars().next().map_or(0, char::len_utf8) + 1..];
+    } else if is_align(rest.chars().next()) {
+        rest = &rest[1..];
+    }
+    for flag in &["+", "-", "#", "0"] {
+        rest = rest.strip_prefix(flag).unwrap_or(rest);
+    }
+    rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    if let Some(precision) = rest.strip_prefix('.') {
+        let digits = precision.len() - precision.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return false;
+        }
+        rest = &precision[digits..];
+    }
+    !spec.is_empty() && matches!(rest, "" | "?" | "x?" | "X?" | "x" | "X" | "o" | "b" | "e" | "E")
+}
+
+/// Drops text that's only whitespace and sits between two tags, for
+/// `@spaceless`.
+///
+/// The ends of `markups` count as tags if `ends_are_tags`. Next to
+/// anything that might write text, like a splice or an `@if`, whitespace
+/// is kept.
+fn strip_spaces_between_tags(markups: &mut Vec<ast::Markup>, ends_are_tags: bool) {
+    for markup in markups.iter_mut() {
+        match *markup {
+            // Whitespace in these is part of the content
+            ast::Markup::Element { ref name, .. } if is_preformatted_element(&name.to_string()) => {},
+            ast::Markup::Element { body: ast::ElementBody::Block { ref mut block }, .. }
+            | ast::Markup::ConditionalComment { body: ref mut block, .. } => {
+                strip_spaces_between_tags(&mut block.markups, true);
+            },
+            ast::Markup::Block(ref mut block)
+            | ast::Markup::Cfg { body: ref mut block, .. }
+            | ast::Markup::Around { body: ref mut block, .. } => {
+                strip_spaces_between_tags(&mut block.markups, false);
+            },
+            ast::Markup::Special { ref mut segments } => {
+                for segment in segments {
+                    strip_spaces_between_tags(&mut segment.body.markups, false);
+                }
+            },
+            ast::Markup::Match { ref mut arms, .. } => {
+                for arm in arms {
+                    strip_spaces_between_tags(&mut arm.body.markups, false);
+                }
+            },
+            ast::Markup::Separated { ref mut body, ref mut sep, .. } => {
+                strip_spaces_between_tags(&mut body.markups, false);
+                strip_spaces_between_tags(&mut sep.markups, false);
+            },
+            ast::Markup::Extends { ref mut blocks, .. } => {
+                for block in blocks {
+                    strip_spaces_between_tags(&mut block.body.markups, false);
+                }
+            },
+            _ => {},
+        }
+    }
+    let is_space = |markup: &ast::Markup| matches!(*markup, ast::Markup::Literal { ref content, .. } if content.trim().is_empty());
+    let is_tag = |markup: Option<&ast::Markup>| match markup {
+        Some(&ast::Markup::Element { .. })
+        | Some(&ast::Markup::ProcessingInstruction { .. })
+        | Some(&ast::Markup::ConditionalComment { .. }) => true,
+        Some(_) => false,
+        None => ends_are_tags,
+    };
+    let mut i = 0;
+    while i < markups.len() {
+        if !is_space(&markups[i]) {
+            i += 1;
+            continue;
+        }
+        // Treat a run of whitespace as one
+        let end = markups[i..].iter().position(|markup| !is_space(markup)).map_or(markups.len(), |n| i + n);
+        let before = if i == 0 { None } else { markups.get(i - 1) };
+        if is_tag(before) && is_tag(markups.get(end)) {
+            markups.drain(i..end);
+        } else {
+            i = end;
+        }
+    }
+}
+
+/// Finds the body of a splice like `(maud::html! { ... })`.
+///
+/// A bare `html!` is left alone, since it might refer to some other macro.
+/// So are templates with options, like `html!(minify = true, { ... })`.
+fn nested_html(expr: TokenStream) -> Option<Group> {
+    let tokens = expr.into_iter().collect::<Vec<_>>();
+    let is_punct = |token: &TokenTree, c: char| matches!(*token, TokenTree::Punct(ref punct) if punct.as_char() == c);
+    let is_ident = |token: &TokenTree, name: &str| matches!(*token, TokenTree::Ident(ref ident) if ident.to_string() == name);
+    let start = match tokens.len() {
+        6 if is_ident(&tokens[0], "maud") && is_punct(&tokens[1], ':') && is_punct(&tokens[2], ':') => 3,
+        8 if is_punct(&tokens[0], ':') && is_ident(&tokens[2], "maud") && is_punct(&tokens[3], ':') => 5,
+        _ => return None,
+    };
+    match tokens[start..] {
+        [ref name, ref bang, TokenTree::Group(ref body)] if is_ident(name, "html") && is_punct(bang, '!') => {
+            if crate::options::has_options(&body.stream()) {
+                None
+            } else {
+                Some(body.clone())
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Splits a splice like `(value ?? "error")` at its `??`.
+///
+/// Since `value??` is a valid expression, the `??` only counts if what
+/// follows can't continue it, like a literal or an element.
+fn split_catch(expr: TokenStream) -> Option<(TokenStream, TokenStream)> {
+    let tokens = expr.into_iter().collect::<Vec<_>>();
+    let is_question = |token: &TokenTree| matches!(*token, TokenTree::Punct(ref punct) if punct.as_char() == '?');
+    let position = (1..tokens.len().saturating_sub(2)).find(|&i| {
+        is_question(&tokens[i]) && is_question(&tokens[i + 1]) && match tokens[i + 2] {
+            TokenTree::Literal(_) => true,
+            TokenTree::Ident(ref ident) => ident.to_string() != "as",
+            TokenTree::Group(ref group) => group.delimiter() == Delimiter::Brace,
+            TokenTree::Punct(_) => false,
+        }
+    })?;
+    Some((
+        tokens[..position].iter().cloned().collect(),
+        tokens[position + 2..].iter().cloned().collect(),
+    ))
+}
+
+/// Splits a splice like `(value else "N/A")` at its `else`.
+///
+/// An `else` right after a `{ ... }` block belongs to an `if`, so
+/// `(if a { b } else { c })` is left alone.
+fn split_else(expr: TokenStream) -> Option<(TokenStream, TokenStream)> {
+    let tokens = expr.into_iter().collect::<Vec<_>>();
+    let position = tokens.iter().enumerate().rposition(|(i, token)| match *token {
+        TokenTree::Ident(ref ident) if ident.to_string() == "else" && i > 0 => !matches!(
+            tokens[i - 1],
+            TokenTree::Group(ref group) if group.delimiter() == Delimiter::Brace
+        ),
+        _ => false,
+    })?;
+    if position + 1 == tokens.len() {
+        return None;
+    }
+    Some((
+        tokens[..position].iter().cloned().collect(),
+        tokens[position + 1..].iter().cloned().collect(),
+    ))
+}
@@ -0,0 +1,67 @@
+use proc_macro::{Ident, TokenStream, TokenTree};
+
+use crate::ast::*;
+
+/// Returns the variables that a template splices, or `None` if it uses
+/// syntax that the runtime interpreter in `maud::reload` doesn't support.
+pub fn variables(markups: &[Markup]) -> Option<Vec<Ident>> {
+    let mut variables = Vec::new();
+    markups_variables(markups, &mut variables)?;
+    Some(variables)
+}
+
+fn markups_variables(markups: &[Markup], variables: &mut Vec<Ident>) -> Option<()> {
+    for markup in markups {
+        match *markup {
+            Markup::Literal { .. } => {},
+            Markup::Block(ref block) => markups_variables(&block.markups, variables)?,
+            Markup::Splice { ref expr, .. } => splice_variable(expr, variables)?,
+            Markup::Element { ref attrs, ref body, .. } => {
+                for attr in attrs {
+                    match *attr {
+                        Attr::Class { ref name, toggler: None, .. } | Attr::Id { ref name, .. } => {
+                            name_variable(name, variables)?;
+                        },
+                        Attr::Attribute { ref attribute } if attribute.cfg.is_none() => {
+                            match attribute.attr_type {
+                                AttrType::Normal { value: Markup::Literal { .. } }
+                                | AttrType::Empty { toggler: None } => {},
+                                AttrType::Normal { value: Markup::Splice { ref expr, .. } } => {
+                                    splice_variable(expr, variables)?;
+                                },
+                                _ => return None,
+                            }
+                        },
+                        _ => return None,
+                    }
+                }
+                if let ElementBody::Block { ref block } = *body {
+                    markups_variables(&block.markups, variables)?;
+                }
+            },
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+fn name_variable(name: &Markup, variables: &mut Vec<Ident>) -> Option<()> {
+    match *name {
+        Markup::Symbol { .. } | Markup::Literal { .. } => Some(()),
+        Markup::Splice { ref expr, .. } => splice_variable(expr, variables),
+        _ => None,
+    }
+}
+
+fn splice_variable(expr: &TokenStream, variables: &mut Vec<Ident>) -> Option<()> {
+    let mut tokens = expr.clone().into_iter();
+    match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Ident(ident)), None) => {
+            if !variables.iter().any(|variable| variable.to_string() == ident.to_string()) {
+                variables.push(ident);
+            }
+            Some(())
+        },
+        _ => None,
+    }
+}
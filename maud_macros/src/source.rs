@@ -0,0 +1,167 @@
+//! Maps tokens parsed from a template file back to lines in that file.
+//!
+//! Tokens parsed from a string all take the span of the macro call, so
+//! the compiler can't say where in the file they came from. Instead, we
+//! find where each token starts by lexing the file alongside them, and
+//! remember the last one the parser consumed before it failed.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// The text of a template file, and where parsing it went wrong.
+pub struct SourceFile {
+    text: String,
+    /// The start of the last token consumed by the parser.
+    last: Cell<Option<usize>>,
+    /// Where each failed markup was given up on.
+    failures: RefCell<Vec<usize>>,
+}
+
+impl SourceFile {
+    pub fn new(text: String) -> SourceFile {
+        SourceFile {
+            text,
+            last: Cell::new(None),
+            failures: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Finds where each of the top-level tokens of the file starts.
+    pub fn offsets(&self, tokens: &[TokenTree]) -> Option<Rc<[usize]>> {
+        token_offsets(&self.text, tokens, 0).map(Rc::from)
+    }
+
+    /// Finds where the tokens of a group in `parent` start.
+    ///
+    /// The group is matched by its contents, looking back from `pos` first,
+    /// since the group being parsed is usually the one just consumed.
+    pub fn group_offsets(
+        &self,
+        parent: &[TokenTree],
+        parent_offsets: &[usize],
+        pos: usize,
+        inner: &[TokenTree],
+    ) -> Option<Rc<[usize]>> {
+        let inner_text = inner.iter().cloned().collect::<TokenStream>().to_string();
+        let pos = pos.min(parent.len());
+        let index = (0..pos).rev().chain(pos..parent.len()).find(|&i| match parent[i] {
+            TokenTree::Group(ref group) => group.stream().to_string() == inner_text,
+            _ => false,
+        })?;
+        token_offsets(&self.text, inner, parent_offsets[index] + 1).map(Rc::from)
+    }
+
+    /// Records that the parser consumed the token starting at `offset`.
+    pub fn consumed(&self, offset: usize) {
+        self.last.set(Some(offset));
+    }
+
+    /// The number of failures recorded so far.
+    pub fn failure_count(&self) -> usize {
+        self.failures.borrow().len()
+    }
+
+    /// Records a failed markup at the last token consumed, unless a nested
+    /// markup has already recorded it since `count`.
+    pub fn fail(&self, count: usize) {
+        let mut failures = self.failures.borrow_mut();
+        if let (true, Some(offset)) = (failures.len() == count, self.last.get()) {
+            failures.push(offset);
+        }
+    }
+
+    /// The line numbers of the recorded failures, counting from 1.
+    pub fn failed_lines(&self) -> Vec<usize> {
+        let mut lines = self.failures
+            .borrow()
+            .iter()
+            .map(|&offset| self.text[..offset].matches('\n').count() + 1)
+            .collect::<Vec<_>>();
+        lines.dedup();
+        lines
+    }
+}
+
+/// Finds where each token starts in `text`, starting the search at
+/// `start`. Returns `None` if the tokens don't line up with the text, as
+/// happens with doc comments.
+fn token_offsets(text: &str, tokens: &[TokenTree], start: usize) -> Option<Vec<usize>> {
+    let mut offsets = Vec::with_capacity(tokens.len());
+    let mut cursor = start;
+    for token in tokens {
+        cursor = skip_trivia(text, cursor);
+        offsets.push(cursor);
+        cursor = token_end(text, token, cursor)?;
+    }
+    Some(offsets)
+}
+
+/// Finds the end of `token`, which starts at `start`.
+fn token_end(text: &str, token: &TokenTree, start: usize) -> Option<usize> {
+    match *token {
+        TokenTree::Group(ref group) => {
+            let (open, close) = match group.delimiter() {
+                Delimiter::Parenthesis => ('(', ')'),
+                Delimiter::Brace => ('{', '}'),
+                Delimiter::Bracket => ('[', ']'),
+                Delimiter::None => return None,
+            };
+            if !text[start..].starts_with(open) {
+                return None;
+            }
+            let inner = group.stream().into_iter().collect::<Vec<_>>();
+            let mut cursor = start + 1;
+            if let (Some(last), Some(&offset)) = (inner.last(), token_offsets(text, &inner, cursor)?.last()) {
+                cursor = token_end(text, last, offset)?;
+            }
+            cursor = skip_trivia(text, cursor);
+            if text[cursor..].starts_with(close) {
+                Some(cursor + 1)
+            } else {
+                None
+            }
+        },
+        _ => {
+            let token = token.to_string();
+            if text[start..].starts_with(&token) {
+                Some(start + token.len())
+            } else {
+                None
+            }
+        },
+    }
+}
+
+/// Skips whitespace and comments.
+fn skip_trivia(text: &str, mut cursor: usize) -> usize {
+    loop {
+        let rest = &text[cursor..];
+        let trimmed = rest.trim_start();
+        cursor += rest.len() - trimmed.len();
+        if trimmed.starts_with("//") {
+            cursor += trimmed.find('\n').unwrap_or(trimmed.len());
+        } else if trimmed.starts_with("/*") {
+            // Block comments nest
+            let mut depth = 0;
+            let mut i = 0;
+            while i < trimmed.len() {
+                if trimmed[i..].starts_with("/*") {
+                    depth += 1;
+                    i += 2;
+                } else if trimmed[i..].starts_with("*/") {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += trimmed[i..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+            cursor += i;
+        } else {
+            return cursor;
+        }
+    }
+}